@@ -0,0 +1,245 @@
+// compares the mask based analysis with the previous `Vec<PlotPoint>` implementation
+// and the tiled analysis of large images with the analysis of the whole image
+// run with `cargo bench --bench analysis`, the tiled analysis is checked by the tests in `tiled.rs`
+
+use std::time::{Duration, Instant};
+
+use egui_plot::PlotPoint;
+use image::{GrayImage, Luma};
+use imageproc::definitions::{HasBlack, HasWhite};
+use wasm_pore_detection::model::{
+    image_data::{analyze_grayscale, AnalysisParameters, AnalysisResult},
+    job::JobControl,
+    tiled::{analyze_tiled, GrayStrips},
+};
+
+const THRESHOLD: i16 = 100;
+const MIN_FEATURE_SIZE: f32 = 20.0;
+
+// bright material with dark round pores, some of them with small bright spots inside
+fn synthetic_image(size: u32) -> GrayImage {
+    let mut seed: u64 = 0x5eed;
+    let mut random = move || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as u32
+    };
+
+    let mut image = GrayImage::from_fn(size, size, |_, _| Luma([180 + (random() % 60) as u8]));
+
+    for _ in 0..(size * size / 400) {
+        let (center_x, center_y) = (random() % size, random() % size);
+        let radius = 1 + random() % 8;
+
+        for y in center_y.saturating_sub(radius)..(center_y + radius).min(size) {
+            for x in center_x.saturating_sub(radius)..(center_x + radius).min(size) {
+                let distance = (x as f64 - center_x as f64).hypot(y as f64 - center_y as f64);
+                if distance <= radius as f64 {
+                    let value = if radius > 4 && distance < 1.0 {
+                        200
+                    } else {
+                        (random() % 60) as u8
+                    };
+                    image.put_pixel(x, y, Luma([value]));
+                }
+            }
+        }
+    }
+
+    image
+}
+
+// the analysis before the switch to bit masks (whole image, no manual edits)
+fn legacy_analysis(grayscale: &GrayImage, parameters: &AnalysisParameters) -> (usize, usize, f64) {
+    let grayscale_thresh = imageproc::contrast::threshold(
+        grayscale,
+        parameters.threshold.try_into().unwrap(),
+        imageproc::contrast::ThresholdType::Binary,
+    );
+
+    let labels = imageproc::region_labelling::connected_components(
+        &grayscale_thresh,
+        imageproc::region_labelling::Connectivity::Eight,
+        Luma::white(),
+    );
+    let num_labels = labels.iter().max().unwrap_or(&0);
+    let mut labels_to_size = vec![0; *num_labels as usize + 1];
+    labels.enumerate_pixels().for_each(|(_, _, p)| {
+        labels_to_size[p[0] as usize] += 1;
+    });
+
+    let black_labels = imageproc::region_labelling::connected_components(
+        &grayscale_thresh,
+        imageproc::region_labelling::Connectivity::Eight,
+        Luma::black(),
+    );
+    let num_labels_black = black_labels.iter().max().unwrap_or(&0);
+    let mut black_labels_to_size = vec![0; *num_labels_black as usize + 1];
+    black_labels.enumerate_pixels().for_each(|(_, _, p)| {
+        black_labels_to_size[p[0] as usize] += 1;
+    });
+
+    let mut green_pixels = Vec::new();
+    let mut white_pixels = Vec::new();
+    labels.enumerate_pixels().for_each(|(x, y, p)| {
+        if grayscale_thresh.get_pixel(x, y) == &Luma::black()
+            && labels_to_size[p[0] as usize] > parameters.minimal_pore_size_low as i32
+            && labels_to_size[p[0] as usize] < parameters.minimal_pore_size_high as i32
+        {
+            green_pixels.push(PlotPoint::new(x, y));
+        }
+
+        if grayscale_thresh.get_pixel(x, y) == &Luma::white() {
+            white_pixels.push(PlotPoint::new(x, y));
+        }
+    });
+
+    if parameters.included_min_feature_size > 0.0 {
+        black_labels.enumerate_pixels().for_each(|(x, y, p)| {
+            if black_labels_to_size[p[0] as usize] < parameters.included_min_feature_size as i32 {
+                green_pixels.push(PlotPoint::new(x, y));
+
+                if white_pixels.contains(&PlotPoint::new(x, y)) {
+                    white_pixels.retain(|p| p != &PlotPoint::new(x, y));
+                }
+            }
+        });
+    }
+
+    let density = (1.0 - (green_pixels.len() as f64 / white_pixels.len() as f64)) * 100.0;
+
+    (green_pixels.len(), white_pixels.len(), density)
+}
+
+fn assert_same_result(expected: &AnalysisResult, result: &AnalysisResult) {
+    assert!(
+        expected.green_pixels == result.green_pixels,
+        "pore pixels differ"
+    );
+    assert!(
+        expected.white_pixels == result.white_pixels,
+        "material pixels differ"
+    );
+    assert_eq!(expected.density, result.density, "density differs");
+    assert_eq!(
+        expected.label_sizes, result.label_sizes,
+        "label sizes differ"
+    );
+
+    for (expected, result) in [
+        (&expected.pores, &result.pores),
+        (&expected.excluded_pores, &result.excluded_pores),
+    ] {
+        assert_eq!(expected.len(), result.len(), "number of pores differs");
+        for (expected, pore) in expected.iter().zip(result) {
+            assert_eq!(
+                (expected.label, expected.area, expected.centroid),
+                (pore.label, pore.area, pore.centroid),
+                "pores differ"
+            );
+        }
+    }
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+fn main() {
+    let parameters = AnalysisParameters {
+        threshold: THRESHOLD,
+        minimal_pore_size_low: 0.0,
+        minimal_pore_size_high: i32::MAX as f32,
+        included_min_feature_size: MIN_FEATURE_SIZE,
+        region_start: None,
+        region_end: None,
+    };
+
+    println!(
+        "{:>6} {:>12} {:>12} {:>10}",
+        "size", "legacy", "masks", "speedup"
+    );
+
+    for size in [128, 256, 512, 1024] {
+        let image = synthetic_image(size);
+
+        let (legacy, legacy_time) = time(|| legacy_analysis(&image, &parameters));
+        let (result, mask_time) = time(|| {
+            analyze_grayscale(
+                &image,
+                &parameters,
+                &Default::default(),
+                &[],
+                &JobControl::default(),
+            )
+            .unwrap()
+        });
+
+        assert_eq!(legacy.0, result.green_pixels.count(), "pore pixels differ");
+        assert_eq!(
+            legacy.1,
+            result.white_pixels.count(),
+            "material pixels differ"
+        );
+        assert_eq!(legacy.2, result.density, "density differs");
+
+        println!(
+            "{:>6} {:>12.2?} {:>12.2?} {:>9.1}x",
+            size,
+            legacy_time,
+            mask_time,
+            legacy_time.as_secs_f64() / mask_time.as_secs_f64()
+        );
+    }
+
+    // the legacy implementation takes minutes for these sizes
+    for size in [2048, 4472] {
+        let image = synthetic_image(size);
+        let (_, mask_time) = time(|| {
+            analyze_grayscale(
+                &image,
+                &parameters,
+                &Default::default(),
+                &[],
+                &JobControl::default(),
+            )
+            .unwrap()
+        });
+
+        println!("{:>6} {:>12} {:>12.2?}", size, "-", mask_time);
+    }
+
+    println!();
+    println!("{:>6} {:>12} {:>12}", "size", "whole", "tiled");
+
+    for size in [1024, 4472] {
+        let image = synthetic_image(size);
+        let (expected, whole_time) = time(|| {
+            analyze_grayscale(
+                &image,
+                &parameters,
+                &Default::default(),
+                &[],
+                &JobControl::default(),
+            )
+            .unwrap()
+        });
+        let (result, tiled_time) = time(|| {
+            analyze_tiled(
+                &mut GrayStrips::new(&image, 256),
+                &parameters,
+                &Default::default(),
+                &[],
+                &JobControl::default(),
+            )
+            .unwrap()
+            .unwrap()
+        });
+        assert_same_result(&expected, &result);
+
+        println!("{:>6} {:>12.2?} {:>12.2?}", size, whole_time, tiled_time);
+    }
+}
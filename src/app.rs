@@ -1,14 +1,22 @@
 use crate::{
-    model::detection_app::PoreDetectionApp,
+    model::{
+        autosave::{autosave, AUTOSAVE_INTERVAL},
+        detection_app::PoreDetectionApp,
+        history::{redo, track_changes, undo},
+        project::save_project_dialog,
+    },
     view::{
-        debug_window, export_window, load_project_modal, new_project_modal, plot, shortcut_window,
-        sidepanel, top_panel,
+        batch_window, debug_window, depth_profile_window, display_settings_window, export_window,
+        ground_truth_window, history_window, image_export_window, import_report_window,
+        load_project_modal, new_project_modal, plot, relink_modal, restore_session_modal,
+        shortcut_window, sidepanel, statistics_window, threshold_sweep_window, top_panel,
     },
 };
 
 impl eframe::App for PoreDetectionApp {
     fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
         self.receive_image_data(ctx);
+        self.receive_batch_results(ctx);
 
         if ctx.input(|i| i.key_pressed(egui::Key::ArrowRight)) {
             log::info!("Right arrow key pressed");
@@ -57,6 +65,21 @@ impl eframe::App for PoreDetectionApp {
             }
         } else if ctx.input(|i| i.key_pressed(egui::Key::D)) {
             self.debug_window_open = !self.debug_window_open;
+        } else if !ctx.wants_keyboard_input()
+            && ctx.input_mut(|i| {
+                i.consume_key(
+                    egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                    egui::Key::Z,
+                )
+            })
+        {
+            redo(self, ctx);
+        } else if !ctx.wants_keyboard_input()
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z))
+        {
+            undo(self, ctx);
+        } else if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::S)) {
+            save_project_dialog(self, false);
         }
 
         top_panel::display_top_panel(ctx, self);
@@ -67,14 +90,46 @@ impl eframe::App for PoreDetectionApp {
 
         load_project_modal::display_load_project_modal(ctx, self);
 
+        relink_modal::display_relink_modal(ctx, self);
+
+        restore_session_modal::display_restore_session_modal(ctx, self);
+
+        import_report_window::display_import_report_window(ctx, self);
+
         sidepanel::display_sidepanel(ctx, self);
 
         export_window::display_export_window(ctx, self);
 
         debug_window::display_debug_window(ctx, self);
 
+        depth_profile_window::display_depth_profile_window(ctx, self);
+
+        statistics_window::display_statistics_window(ctx, self);
+
+        threshold_sweep_window::display_threshold_sweep_window(ctx, self);
+
+        ground_truth_window::display_ground_truth_window(ctx, self);
+
+        display_settings_window::display_display_settings_window(ctx, self);
+
+        batch_window::display_batch_window(ctx, self);
+
+        image_export_window::display_image_export_window(ctx, self);
+
         plot::display_plot(ctx, self);
 
+        history_window::display_history_window(ctx, self);
+
+        track_changes(self, ctx.input(|i| i.pointer.any_down()));
+
         ctx.request_repaint();
     }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        autosave(self, storage);
+    }
+
+    fn auto_save_interval(&self) -> std::time::Duration {
+        AUTOSAVE_INTERVAL
+    }
 }
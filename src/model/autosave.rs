@@ -0,0 +1,72 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    detection_app::PoreDetectionApp,
+    project::{Project, PROJECT_VERSION},
+};
+
+const AUTOSAVE_KEY: &str = "autosave";
+
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+// the last session with changes that were not saved to a project file, the paths are absolute
+#[derive(Serialize, Deserialize)]
+pub struct AutosaveSession {
+    pub project: Project,
+    pub project_path: Option<PathBuf>,
+}
+
+// the project of the app and a text to compare it with the saved one,
+// the densities are left out because they change with every analysis and not by the user
+pub fn project_state(app: &PoreDetectionApp) -> (Project, String) {
+    let mut project = Project::from_app(app);
+
+    let densities = project
+        .images
+        .iter_mut()
+        .map(|image| image.density.take())
+        .collect::<Vec<_>>();
+    let state = ron::to_string(&project).unwrap_or_default();
+    for (image, density) in project.images.iter_mut().zip(densities) {
+        image.density = density;
+    }
+
+    (project, state)
+}
+
+// called by eframe every `AUTOSAVE_INTERVAL` and on exit
+pub fn autosave(app: &mut PoreDetectionApp, storage: &mut dyn eframe::Storage) {
+    if app.discard_autosave {
+        app.discard_autosave = false;
+        storage.set_string(AUTOSAVE_KEY, String::new());
+    }
+
+    // an empty app doesn't replace the last session, it may have been lost by an accidental `New`
+    if app.images.images.is_empty() || app.session_to_restore.is_some() {
+        return;
+    }
+
+    let (project, state) = project_state(app);
+    if app.saved_state.as_ref() == Some(&state) {
+        storage.set_string(AUTOSAVE_KEY, String::new());
+        return;
+    }
+
+    eframe::set_value(
+        storage,
+        AUTOSAVE_KEY,
+        &AutosaveSession {
+            project,
+            project_path: app.project_path.clone(),
+        },
+    );
+}
+
+// an empty or unreadable entry means there is nothing to restore
+pub fn load_autosave(storage: &dyn eframe::Storage) -> Option<AutosaveSession> {
+    eframe::get_value::<AutosaveSession>(storage, AUTOSAVE_KEY)
+        .filter(|session| session.project.version <= PROJECT_VERSION)
+        .filter(|session| !session.project.images.is_empty())
+}
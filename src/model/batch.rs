@@ -0,0 +1,325 @@
+use std::{
+    collections::VecDeque,
+    panic::AssertUnwindSafe,
+    path::PathBuf,
+    sync::{
+        atomic::Ordering,
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use image::DynamicImage;
+
+use super::{
+    excluded_pores::ExcludedPore,
+    histogram::GreyHistogram,
+    image_data::{
+        add_spatial_statistics, analyze_large_image, filter_labels, AnalysisParameters,
+        AnalysisResult, ImageData,
+    },
+    job::JobControl,
+    manual_mask::ManualOverrides,
+    pipeline::{label_grayscale, PipelineCache},
+};
+
+#[derive(Clone, Debug)]
+pub enum BatchState {
+    Queued,
+    Running(Instant),
+    Done(Duration),
+    Failed(String),
+    Cancelled,
+}
+
+pub struct BatchEntry {
+    pub image_id: u64,
+    pub state: BatchState,
+}
+
+// everything a worker needs, the decoded image is shared with the image list and large images that
+// are not decoded are streamed from disk by the worker
+struct BatchTask {
+    image_id: u64,
+    generation: u64,
+    path: Option<PathBuf>,
+    // shared with the image list, so it is neither copied nor read from disk again
+    image: Option<Arc<DynamicImage>>,
+    is_large: bool,
+    parameters: AnalysisParameters,
+    histogram_region: Option<Option<(u32, u32, u32, u32)>>,
+    manual_overrides: ManualOverrides,
+    excluded_pores: Vec<ExcludedPore>,
+    // only read, filling the caches of every image would keep all of them in memory
+    cache: PipelineCache,
+}
+
+enum BatchMessage {
+    Started(u64),
+    Finished {
+        image_id: u64,
+        generation: u64,
+        result: Result<Box<AnalysisResult>, String>,
+        duration: Duration,
+    },
+    Cancelled(u64),
+}
+
+// a finished analysis, only valid if the image still has the same generation
+pub struct BatchResult {
+    pub image_id: u64,
+    pub generation: u64,
+    pub result: AnalysisResult,
+}
+
+pub struct BatchQueue {
+    pub entries: Vec<BatchEntry>,
+    pub control: Arc<JobControl>,
+    pub workers: usize,
+    receiver: Receiver<BatchMessage>,
+}
+
+impl BatchQueue {
+    pub fn start(images: &[ImageData], image_ids: &[u64], workers: usize) -> Self {
+        let tasks = images
+            .iter()
+            .filter(|image| image_ids.contains(&image.id))
+            .map(|image| BatchTask {
+                image_id: image.id,
+                generation: image.generation,
+                path: image.path.clone(),
+                image: image.image.clone(),
+                is_large: image.is_large(),
+                parameters: image.analysis_parameters(),
+                histogram_region: image.outdated_histogram_region(),
+                manual_overrides: image.manual_overrides.clone(),
+                excluded_pores: image.excluded_pores.clone(),
+                cache: image.pipeline_cache.clone(),
+            })
+            .collect::<VecDeque<_>>();
+        let entries = tasks
+            .iter()
+            .map(|task| BatchEntry {
+                image_id: task.image_id,
+                state: BatchState::Queued,
+            })
+            .collect();
+
+        let workers = workers.clamp(1, tasks.len().max(1));
+        let tasks = Arc::new(Mutex::new(tasks));
+        let control = Arc::new(JobControl::default());
+        let (sender, receiver) = channel();
+
+        for _ in 0..workers {
+            let (tasks, control, sender) = (tasks.clone(), control.clone(), sender.clone());
+            std::thread::spawn(move || run_worker(&tasks, &control, &sender));
+        }
+
+        Self {
+            entries,
+            control,
+            workers,
+            receiver,
+        }
+    }
+
+    // updates the states and returns the finished results
+    pub fn poll(&mut self) -> Vec<BatchResult> {
+        let mut results = Vec::new();
+
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                BatchMessage::Started(image_id) => {
+                    self.set_state(image_id, BatchState::Running(Instant::now()));
+                }
+                BatchMessage::Finished {
+                    image_id,
+                    generation,
+                    result: Ok(result),
+                    duration,
+                } => {
+                    self.set_state(image_id, BatchState::Done(duration));
+                    results.push(BatchResult {
+                        image_id,
+                        generation,
+                        result: *result,
+                    });
+                }
+                BatchMessage::Finished {
+                    image_id,
+                    result: Err(err),
+                    ..
+                } => {
+                    log::warn!("Batch analysis of image {} failed: {}", image_id, err);
+                    self.set_state(image_id, BatchState::Failed(err));
+                }
+                BatchMessage::Cancelled(image_id) => {
+                    self.set_state(image_id, BatchState::Cancelled);
+                }
+            }
+        }
+
+        if self.control.is_cancelled() {
+            self.entries
+                .iter_mut()
+                .filter(|entry| matches!(entry.state, BatchState::Queued))
+                .for_each(|entry| entry.state = BatchState::Cancelled);
+        }
+
+        results
+    }
+
+    pub fn state(&self, image_id: u64) -> Option<&BatchState> {
+        self.entries
+            .iter()
+            .find(|entry| entry.image_id == image_id)
+            .map(|entry| &entry.state)
+    }
+
+    fn set_state(&mut self, image_id: u64, state: BatchState) {
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.image_id == image_id)
+        {
+            entry.state = state;
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.control.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.control.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn count(&self, f: impl Fn(&BatchState) -> bool) -> usize {
+        self.entries.iter().filter(|entry| f(&entry.state)).count()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.count(|state| matches!(state, BatchState::Queued | BatchState::Running(_))) == 0
+    }
+
+    // estimated from the mean duration of the finished images
+    pub fn eta(&self) -> Option<Duration> {
+        let durations = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry.state {
+                BatchState::Done(duration) => Some(duration),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if durations.is_empty() {
+            return None;
+        }
+
+        let mean = durations.iter().sum::<Duration>() / durations.len() as u32;
+        let remaining =
+            self.count(|state| matches!(state, BatchState::Queued | BatchState::Running(_)));
+
+        Some(mean * remaining.div_ceil(self.workers) as u32)
+    }
+}
+
+fn run_worker(
+    tasks: &Mutex<VecDeque<BatchTask>>,
+    control: &JobControl,
+    sender: &Sender<BatchMessage>,
+) {
+    loop {
+        if control.is_cancelled() {
+            return;
+        }
+        if control.is_paused() {
+            std::thread::sleep(Duration::from_millis(50));
+            continue;
+        }
+
+        let Some(task) = tasks.lock().unwrap().pop_front() else {
+            return;
+        };
+
+        let start = Instant::now();
+        let _ = sender.send(BatchMessage::Started(task.image_id));
+
+        let message = match analyze_task(&task, control).transpose() {
+            Some(result) => BatchMessage::Finished {
+                image_id: task.image_id,
+                generation: task.generation,
+                result,
+                duration: start.elapsed(),
+            },
+            None => BatchMessage::Cancelled(task.image_id),
+        };
+        if sender.send(message).is_err() {
+            // the queue was dropped
+            return;
+        }
+    }
+}
+
+// `Ok(None)` if the batch was cancelled during the analysis
+fn analyze_task(
+    task: &BatchTask,
+    control: &JobControl,
+) -> Result<Option<Box<AnalysisResult>>, String> {
+    if task.is_large {
+        return std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let result = analyze_large_image(
+                task.image.as_deref(),
+                task.path.as_deref(),
+                &task.parameters,
+                task.histogram_region,
+                &task.manual_overrides,
+                &task.excluded_pores,
+                control,
+            )?;
+
+            Ok(result.map(Box::new))
+        }))
+        .map_err(|_| "analysis failed".to_string())?;
+    }
+
+    let image = task.image.as_ref().ok_or("image is not loaded")?;
+
+    let grayscale = match task.cache.grayscale() {
+        Some(grayscale) => grayscale,
+        None => Arc::new(image.grayscale().to_luma8()),
+    };
+
+    // a panic in the analysis should only fail this image and not the whole batch
+    std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let labelling = match task.cache.labelling(task.parameters.threshold) {
+            Some(labelling) => labelling,
+            None => Arc::new(label_grayscale(
+                &grayscale,
+                task.parameters.threshold,
+                control,
+            )?),
+        };
+
+        let mut result = filter_labels(
+            &labelling,
+            &task.parameters,
+            &task.manual_overrides,
+            &task.excluded_pores,
+            control,
+        )?;
+        add_spatial_statistics(
+            &mut result,
+            &task.parameters,
+            grayscale.width(),
+            grayscale.height(),
+        );
+        result.histogram = task
+            .histogram_region
+            .map(|region| GreyHistogram::new(&grayscale, region));
+
+        Some(Box::new(result))
+    }))
+    .map_err(|_| "analysis failed".to_string())
+}
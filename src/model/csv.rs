@@ -0,0 +1,242 @@
+use std::path::Path;
+
+use calamine::{Data, Range};
+
+use super::{
+    detection_app::PoreDetectionApp, excel_import::read_images, history::ImageState,
+    image_data::ImageData, project::ProjectImage,
+};
+
+// fields with the separator, quotes or line breaks are quoted
+pub fn write_csv_line(fields: &[impl AsRef<str>], separator: char) -> String {
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([separator, '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    fields.join(&separator.to_string()) + "\r\n"
+}
+
+// the separator is guessed from the header, a tab for TSV, `;` for files with decimal commas, otherwise `,`
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let text = text.trim_start_matches('\u{feff}');
+    let header = text.lines().next().unwrap_or_default();
+    let separator = ['\t', ';', ',']
+        .into_iter()
+        .find(|separator| header.contains(*separator))
+        .unwrap_or(',');
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows
+}
+
+// the rows as a sheet, so they can be read like an Excel import
+fn csv_range(rows: &[Vec<String>]) -> Range<Data> {
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if rows.is_empty() || width == 0 {
+        return Range::empty();
+    }
+
+    let mut range = Range::new((0, 0), (rows.len() as u32 - 1, width as u32 - 1));
+    for (y, row) in rows.iter().enumerate() {
+        for (x, field) in row.iter().enumerate() {
+            if !field.trim().is_empty() {
+                range.set_value((y as u32, x as u32), Data::String(field.clone()));
+            }
+        }
+    }
+
+    range
+}
+
+// the loaded image with the same path or the only one with the same file name
+fn find_loaded_image<'a>(images: &'a [ImageData], path: &Path) -> Result<&'a ImageData, String> {
+    let file_name = path.file_name().unwrap_or_default();
+    if let Some(image) = images
+        .iter()
+        .find(|image| image.path.as_deref() == Some(path))
+    {
+        return Ok(image);
+    }
+
+    // specimen folders often reuse file names, so only a unique name is enough
+    let same_name = images
+        .iter()
+        .filter(|image| image.path.as_ref().and_then(|path| path.file_name()) == Some(file_name))
+        .collect::<Vec<_>>();
+    match same_name[..] {
+        [image] => Ok(image),
+        [] => Err(format!("{:?} is not loaded", file_name)),
+        _ => Err(format!(
+            "ambiguous file name {:?}, {} loaded images have it, use the full path",
+            file_name,
+            same_name.len()
+        )),
+    }
+}
+
+// applies the parameters of every row to the loaded image with the same path or the only one with the same file name,
+// the changed images are analyzed again, returns the problems of the rows
+pub fn apply_parameters_csv(
+    app: &mut PoreDetectionApp,
+    path: &Path,
+) -> Result<Vec<String>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not open {:?}: {}", path, err))?;
+    let range = csv_range(&parse_csv(&text));
+    let folder = path.parent().unwrap_or(Path::new(""));
+    let sheet = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut problems = Vec::new();
+    let rows = read_images(&sheet, &range, folder, &mut problems, |image_path| {
+        find_loaded_image(&app.images.images, &image_path).map(ProjectImage::from_image)
+    });
+
+    let mut changed = Vec::new();
+    for row in rows {
+        let Some(image) = app
+            .images
+            .images
+            .iter_mut()
+            .find(|image| image.path.as_ref() == Some(&row.path))
+        else {
+            continue;
+        };
+
+        let before = ImageState::of(image);
+        row.apply_parameters(image);
+        if ImageState::of(image) != before {
+            app.history
+                .record_image_edit(image, before, "Parameters from CSV");
+            changed.push(image.id);
+        }
+    }
+    log::info!("Applied parameters to {} images", changed.len());
+
+    if !changed.is_empty() {
+        app.reanalyze_in_batch(&changed);
+    }
+
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn image(path: &str) -> ImageData {
+        ImageData {
+            path: Some(PathBuf::from(path)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn quoted_fields_keep_separators_quotes_and_line_breaks() {
+        let fields = ["a;b", "say \"hi\"", "two\nlines", "12,5", ""];
+        let line = write_csv_line(&fields, ';');
+        assert_eq!(line, "\"a;b\";\"say \"\"hi\"\"\";\"two\nlines\";12,5;\r\n");
+
+        let text = write_csv_line(&["File Path", "Density"], ';') + &line;
+        assert_eq!(
+            parse_csv(&text),
+            vec![vec!["File Path", "Density"], fields.to_vec()]
+        );
+    }
+
+    #[test]
+    fn the_separator_is_guessed_from_the_header() {
+        // excel writes a byte order mark, the last line may not end with a line break
+        let text = "\u{feff}File Path\tThreshold\r\n\"a,b.png\"\t80";
+        assert_eq!(
+            parse_csv(text),
+            vec![vec!["File Path", "Threshold"], vec!["a,b.png", "80"]]
+        );
+        assert_eq!(
+            parse_csv("File Path,Threshold\na;b.png,80\n"),
+            vec![vec!["File Path", "Threshold"], vec!["a;b.png", "80"]]
+        );
+        assert_eq!(
+            parse_csv("File Path\na.png"),
+            vec![vec!["File Path"], vec!["a.png"]]
+        );
+    }
+
+    #[test]
+    fn parameters_are_read_for_loaded_images() {
+        let images = [
+            image("/specimen/a.png"),
+            image("/specimen_1/b.png"),
+            image("/specimen_2/b.png"),
+        ];
+        let text = "Filename;Threshold;Lower Pore Size [px];Density\n\
+                    a.png;90;12,5;\n\
+                    b.png;80;;\n\
+                    /specimen_2/b.png;70;;\n\
+                    c.png;60;;\n\
+                    Total;;;33,3\n";
+
+        let mut problems = Vec::new();
+        let rows = read_images(
+            "parameters.csv",
+            &csv_range(&parse_csv(text)),
+            Path::new("/specimen"),
+            &mut problems,
+            |path| find_loaded_image(&images, &path).map(ProjectImage::from_image),
+        );
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].path, Path::new("/specimen/a.png"));
+        assert_eq!(rows[0].threshold, 90);
+        assert_eq!(rows[0].minimal_pore_size_low, 12.5);
+        // the full path picks one of the images with the same name
+        assert_eq!(rows[1].path, Path::new("/specimen_2/b.png"));
+        assert_eq!(rows[1].threshold, 70);
+        assert_eq!(
+            problems,
+            vec![
+                "2 of 4 rows were imported",
+                "parameters.csv row 3: ambiguous file name \"b.png\", 2 loaded images have it, use the full path, the row is skipped",
+                "parameters.csv row 5: \"c.png\" is not loaded, the row is skipped",
+            ]
+        );
+    }
+}
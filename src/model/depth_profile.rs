@@ -0,0 +1,123 @@
+use core::fmt;
+use std::collections::BTreeMap;
+
+use egui_plot::PlotPoint;
+
+use super::pixel_mask::PixelMask;
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+pub enum ReferenceLine {
+    TopEdge,
+    BottomEdge,
+    LeftEdge,
+    RightEdge,
+    // start and end of a user drawn line in plot coordinates (y axis pointing up)
+    Custom(
+        #[serde(with = "super::project::plot_point")] PlotPoint,
+        #[serde(with = "super::project::plot_point")] PlotPoint,
+    ),
+}
+
+impl fmt::Display for ReferenceLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReferenceLine::TopEdge => write!(f, "Top edge"),
+            ReferenceLine::BottomEdge => write!(f, "Bottom edge"),
+            ReferenceLine::LeftEdge => write!(f, "Left edge"),
+            ReferenceLine::RightEdge => write!(f, "Right edge"),
+            ReferenceLine::Custom(start, end) => write!(
+                f,
+                "Line ({:.2}, {:.2}) - ({:.2}, {:.2})",
+                start.x, start.y, end.x, end.y
+            ),
+        }
+    }
+}
+
+impl ReferenceLine {
+    // distance of the center of the pixel (in image coordinates) to the reference line, a drawn line has
+    // pixels on both sides, they are positive on its right (looking from start to end on the screen)
+    fn distance(&self, x: u32, y: u32, width: f64, height: f64) -> f64 {
+        let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+
+        match self {
+            ReferenceLine::TopEdge => py,
+            ReferenceLine::BottomEdge => height - py,
+            ReferenceLine::LeftEdge => px,
+            ReferenceLine::RightEdge => width - px,
+            ReferenceLine::Custom(start, end) => {
+                // the line is stored in plot coordinates, the pixels are in image coordinates
+                let (x1, y1) = (start.x, height - start.y);
+                let (x2, y2) = (end.x, height - end.y);
+                let (dx, dy) = (x2 - x1, y2 - y1);
+                let length = (dx * dx + dy * dy).sqrt();
+
+                // a line without a direction has no sides
+                if length == 0.0 {
+                    ((px - x1).powi(2) + (py - y1).powi(2)).sqrt()
+                } else {
+                    (dx * (py - y1) - dy * (px - x1)) / length
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DepthBand {
+    // negative on the left of a drawn line
+    pub start: f64,
+    pub end: f64,
+    pub pore_pixels: usize,
+    pub material_pixels: usize,
+}
+
+impl DepthBand {
+    // pore / material * 100, the density of the whole image is (1 - pore / material) * 100,
+    // a band with only pores is 100 % and `None` if it has no analyzed pixels at all
+    pub fn porosity(&self) -> Option<f64> {
+        if self.material_pixels == 0 {
+            return (self.pore_pixels > 0).then_some(100.0);
+        }
+
+        Some(self.pore_pixels as f64 / self.material_pixels as f64 * 100.0)
+    }
+}
+
+pub fn compute_depth_profile(
+    green_pixels: &PixelMask,
+    white_pixels: &PixelMask,
+    reference: ReferenceLine,
+    band_width: f64,
+) -> Vec<DepthBand> {
+    let band_width = band_width.max(1.0);
+    let (width, height) = (green_pixels.width() as f64, green_pixels.height() as f64);
+
+    let band_index =
+        |(x, y): (u32, u32)| (reference.distance(x, y, width, height) / band_width).floor() as i64;
+
+    // pore and material pixels per band, the bands of a drawn line start on its left
+    let mut counts: BTreeMap<i64, (usize, usize)> = BTreeMap::new();
+    for pixel in green_pixels.iter() {
+        counts.entry(band_index(pixel)).or_default().0 += 1;
+    }
+    for pixel in white_pixels.iter() {
+        counts.entry(band_index(pixel)).or_default().1 += 1;
+    }
+
+    let (Some(&first), Some(&last)) = (counts.keys().next(), counts.keys().next_back()) else {
+        return Vec::new();
+    };
+    // edges always start at 0 and empty bands in between are kept
+    (first.min(0)..=last)
+        .map(|i| {
+            let (pore_pixels, material_pixels) = counts.get(&i).copied().unwrap_or_default();
+            DepthBand {
+                start: i as f64 * band_width,
+                end: (i + 1) as f64 * band_width,
+                pore_pixels,
+                material_pixels,
+            }
+        })
+        .collect()
+}
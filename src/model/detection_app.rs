@@ -1,24 +1,42 @@
+use std::path::PathBuf;
+
 use egui::{Pos2, TextureHandle};
 use egui_extras::install_image_loaders;
 use image::DynamicImage;
 
-use crate::view::{debug_window::DebugInfo, export_window::ExportDecimalFormat};
+use crate::view::{
+    batch_window::BatchSettings,
+    debug_window::DebugInfo,
+    depth_profile_window::DepthProfileSettings,
+    display_settings_window::{OverlaySettings, PoreColoring},
+    export_window::ExportDecimalFormat,
+    histogram::HistogramSettings,
+    image_export_window::ImageExportSettings,
+    plot::{BrushSettings, PlotTool},
+    threshold_sweep_window::ThresholdSweepState,
+};
 
-use super::images::Images;
+use super::{
+    autosave::{load_autosave, AutosaveSession},
+    batch::{BatchQueue, BatchState},
+    history::History,
+    image_data::{AnalysisResult, ImageData},
+    images::Images,
+    job::AnalysisJob,
+    overlay::{blend_pixel, draw_overlay},
+    relink::PendingProject,
+};
 
 #[derive(Default)]
 pub struct PoreDetectionApp {
     pub image_to_display: Option<TextureHandle>,
     pub region_selector: (Option<Pos2>, Option<Pos2>),
     pub region: (Option<Pos2>, Option<Pos2>),
+    pub plot_tool: PlotTool,
+    pub brush: BrushSettings,
+    pub histogram_settings: HistogramSettings,
     pub images: Images,
-    pub join_handle: Option<
-        std::thread::JoinHandle<(
-            std::vec::Vec<egui_plot::PlotPoint>,
-            std::vec::Vec<egui_plot::PlotPoint>,
-            f64,
-        )>,
-    >,
+    pub analysis_jobs: Vec<AnalysisJob>,
     pub export_window_open: bool,
     pub debug_window_open: bool,
     pub debug_info: DebugInfo,
@@ -26,12 +44,42 @@ pub struct PoreDetectionApp {
     pub export_decimal_format: ExportDecimalFormat,
     pub new_project_model_open: bool,
     pub load_project_model_open: bool,
+    pub depth_profile_window_open: bool,
+    pub depth_profile_settings: DepthProfileSettings,
+    pub statistics_window_open: bool,
+    pub threshold_sweep_window_open: bool,
+    pub threshold_sweep: ThresholdSweepState,
+    pub ground_truth_window_open: bool,
+    pub ground_truth_overlay: bool,
+    pub ground_truth_error: Option<String>,
+    pub display_settings_window_open: bool,
+    pub overlay_settings: OverlaySettings,
+    pub batch_window_open: bool,
+    pub batch_settings: BatchSettings,
+    pub batch: Option<BatchQueue>,
+    // where the project was opened from or last saved to
+    pub project_path: Option<PathBuf>,
+    // waits in the relink dialog until its missing images are found
+    pub pending_project: Option<PendingProject>,
+    // the project as it was last saved or opened, see `project_state`
+    pub saved_state: Option<String>,
+    // offered when the app starts after a session that was not saved
+    pub session_to_restore: Option<AutosaveSession>,
+    pub discard_autosave: bool,
+    pub history: History,
+    pub import_problems: Vec<String>,
+    pub history_window_open: bool,
+    pub image_export_window_open: bool,
+    pub image_export_settings: ImageExportSettings,
 }
 
 impl PoreDetectionApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         install_image_loaders(&cc.egui_ctx);
-        Default::default()
+        Self {
+            session_to_restore: cc.storage.and_then(load_autosave),
+            ..Default::default()
+        }
     }
 
     pub fn reload_image(&mut self, selected_image: Option<usize>) {
@@ -45,44 +93,275 @@ impl PoreDetectionApp {
             self.images.selected = Some(0);
         }
 
-        self.join_handle = Some(self.images.images[selected].analyze_image());
+        // only the selected image keeps its cached stages and labels, they take a multiple of the memory of the image
+        if self.images.prev_selected != self.images.selected {
+            self.images
+                .images
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| *i != selected)
+                .for_each(|(_, image)| {
+                    image.pipeline_cache.clear();
+                    image.labels = None;
+                });
+        }
+
+        self.reanalyze_image(selected);
+    }
+
+    // analyzes the image again without selecting it, e.g. after an undo of another image
+    pub fn reanalyze_image(&mut self, index: usize) {
+        self.cancel_analysis(self.images.images[index].id);
+        let job = self.images.images[index].analyze_image();
+        self.analysis_jobs.push(job);
+    }
+
+    // analyzes a downsampled copy for instant feedback while a slider is dragged,
+    // `reload_image` has to be called once the drag ends
+    pub fn preview_image(&mut self, selected_image: usize) {
+        self.cancel_analysis(self.images.images[selected_image].id);
+
+        match self.images.images[selected_image].preview_image() {
+            Some(job) => self.analysis_jobs.push(job),
+            None => self.reload_image(Some(selected_image)),
+        }
+    }
+
+    // the running analyses of this image are outdated once its parameters change
+    pub fn cancel_analysis(&self, image_id: u64) {
+        self.analysis_jobs
+            .iter()
+            .filter(|job| job.image_id == image_id)
+            .for_each(|job| job.control.cancel());
+    }
+
+    // for images whose parameters were changed without selecting them, results of analyses started before are
+    // discarded, a running batch is cancelled and its unfinished images are analyzed again with these images
+    pub fn reanalyze_in_batch(&mut self, image_ids: &[u64]) {
+        let mut image_ids = image_ids.to_vec();
+        for image in self
+            .images
+            .images
+            .iter_mut()
+            .filter(|image| image_ids.contains(&image.id))
+        {
+            image.generation += 1;
+        }
+        for image_id in &image_ids {
+            self.cancel_analysis(*image_id);
+        }
+
+        if let Some(batch) = self.batch.take().filter(|batch| !batch.is_finished()) {
+            batch.cancel();
+            let unfinished = batch
+                .entries
+                .iter()
+                .filter(|entry| {
+                    matches!(entry.state, BatchState::Queued | BatchState::Running(_))
+                        && !image_ids.contains(&entry.image_id)
+                })
+                .map(|entry| entry.image_id)
+                .collect::<Vec<_>>();
+            image_ids.extend(unfinished);
+        }
+
+        self.batch = Some(BatchQueue::start(
+            &self.images.images,
+            &image_ids,
+            self.batch_settings.workers,
+        ));
+    }
+
+    // selects `index` after images were added or removed, the list may be empty
+    fn select_after_list_change(&mut self, index: usize) {
+        if self.images.images.is_empty() {
+            self.images.selected = None;
+            self.image_to_display = None;
+        } else {
+            let index = index.min(self.images.images.len() - 1);
+            self.reload_image(Some(index));
+            self.image_to_display = self.images.images[index].image_handle.clone();
+        }
+        self.images.prev_selected = None;
+    }
+
+    pub fn insert_images(&mut self, index: usize, images: Vec<ImageData>) {
+        self.images.images.splice(index..index, images);
+        self.select_after_list_change(index);
+    }
+
+    pub fn remove_images(&mut self, index: usize, count: usize) -> Vec<ImageData> {
+        let removed = self
+            .images
+            .images
+            .drain(index..index + count)
+            .collect::<Vec<_>>();
+        for image in &removed {
+            self.cancel_analysis(image.id);
+            image.pipeline_cache.clear();
+        }
+
+        let selected = match self.images.selected {
+            Some(selected) if selected >= index + count => selected - count,
+            Some(selected) => selected.min(index),
+            None => index,
+        };
+        self.select_after_list_change(selected);
+
+        removed
+    }
+
+    pub fn is_analyzing(&self, image_id: u64) -> bool {
+        self.analysis_jobs
+            .iter()
+            .any(|job| job.image_id == image_id && !job.control.is_cancelled())
     }
 
     pub fn receive_image_data(&mut self, ctx: &egui::Context) {
         // [TODO] move this to another thread bc painting blocks the main thread for a short time
-        // receive from channel
-        if let Some(handle) = &self.join_handle {
-            if handle.is_finished() {
-                let handle = self.join_handle.take().unwrap();
-                let (green_pixels, white_pixels, density) = handle.join().unwrap();
+        let (finished, running): (Vec<_>, Vec<_>) = std::mem::take(&mut self.analysis_jobs)
+            .into_iter()
+            .partition(|job| job.is_finished());
+        self.analysis_jobs = running;
 
-                let selected_img = self.images.selected.unwrap_or(0);
-
-                self.images.images[selected_img].green_pixels = Some(green_pixels.clone());
-                self.images.images[selected_img].density = Some(density);
+        for job in finished {
+            // cancelled jobs stop early without a result
+            if let Some(result) = job.handle.join().unwrap() {
+                self.apply_analysis_result(ctx, job.image_id, job.generation, job.preview, result);
+            }
+        }
+    }
 
-                // draw a green pixel for each black pixel that is part of a group with a size greater than the users minimal pore size
-                if let Some(path) = &self.images.images[selected_img].path {
-                    log::info!("Drawing green pixels on image: {:?}", path);
+    pub fn receive_batch_results(&mut self, ctx: &egui::Context) {
+        let Some(batch) = &mut self.batch else {
+            return;
+        };
 
-                    let image = self.images.images[selected_img].image.clone().unwrap();
-                    let mut image = image.to_rgba8();
-                    let green_pixel = image::Rgba([0, 255, 13, 204]);
-                    let white_pixel = image::Rgba([255, 255, 255, 127]);
+        for batch_result in batch.poll() {
+            self.apply_analysis_result(
+                ctx,
+                batch_result.image_id,
+                batch_result.generation,
+                false,
+                batch_result.result,
+            );
+        }
+    }
 
-                    green_pixels.iter().for_each(|pixel| {
-                        image.put_pixel(pixel.x as u32, pixel.y as u32, green_pixel);
-                    });
+    // results are only stored on the image they were computed for and only if its parameters did not change since
+    fn apply_analysis_result(
+        &mut self,
+        ctx: &egui::Context,
+        image_id: u64,
+        generation: u64,
+        preview: bool,
+        result: AnalysisResult,
+    ) {
+        let Some(index) = self
+            .images
+            .images
+            .iter()
+            .position(|image| image.id == image_id)
+        else {
+            log::info!("Discard result of removed image {}", image_id);
+            return;
+        };
 
-                    white_pixels.iter().for_each(|pixel| {
-                        image.put_pixel(pixel.x as u32, pixel.y as u32, white_pixel);
-                    });
+        let image = &mut self.images.images[index];
+        if image.generation != generation {
+            log::info!(
+                "Discard stale result of image {} (generation {}, current {})",
+                image_id,
+                generation,
+                image.generation
+            );
+            return;
+        }
 
-                    self.image_to_display =
-                        Some(load_texture_into_ctx(ctx, &DynamicImage::ImageRgba8(image)));
-                }
+        if preview {
+            image.preview = Some(result);
+        } else {
+            image.apply_analysis_result(result, self.depth_profile_settings.band_width);
+            // results of the other images would keep the labels and stages of every image in memory
+            if self.images.selected != Some(index) {
+                image.labels = None;
+                image.pipeline_cache.clear();
             }
         }
+
+        if self.images.selected == Some(index) {
+            self.update_image_to_display(ctx);
+        }
+    }
+
+    pub fn update_image_to_display(&mut self, ctx: &egui::Context) {
+        let selected_img = self.images.selected.unwrap_or(0);
+        let current_image = &self.images.images[selected_img];
+
+        // a preview is drawn on the downsampled image, the plot stretches it to the size of the image
+        let preview = current_image
+            .preview
+            .as_ref()
+            .zip(current_image.preview_base());
+        let pixels = match &preview {
+            Some((preview, _)) => Some((&preview.green_pixels, &preview.white_pixels)),
+            None => current_image
+                .green_pixels
+                .as_ref()
+                .zip(current_image.white_pixels.as_ref()),
+        };
+
+        // draw a green pixel for each black pixel that is part of a group with a size greater than the users minimal pore size
+        if let (Some(path), Some((green_pixels, white_pixels))) = (&current_image.path, pixels) {
+            log::info!("Drawing green pixels on image: {:?}", path);
+
+            // large images are never copied at full size, the full resolution result is drawn on their downsampled image
+            let (mut image, factor) = match (&preview, &current_image.downsampled) {
+                (Some((_, base)), _) => (base.image.clone(), 1),
+                (None, Some(downsampled)) => (downsampled.image.clone(), downsampled.factor),
+                (None, None) => (current_image.image.as_ref().unwrap().to_rgba8(), 1),
+            };
+            // many pixels of a large image fall on one downsampled pixel, each of them is blended on the
+            // original pixel so they don't add up
+            let base = (factor > 1).then(|| image.clone());
+            let settings = &self.overlay_settings;
+            // the labels of the preview are not kept, so it can only be drawn in one colour
+            let pore_coloring = match preview {
+                Some(_) => PoreColoring::Uniform,
+                None => settings.pore_coloring,
+            };
+
+            // false positives and false negatives are only known for the full resolution result
+            let comparison = match (self.ground_truth_overlay, &preview) {
+                (true, None) => current_image.segmentation_comparison.as_ref(),
+                _ => None,
+            };
+            draw_overlay(
+                current_image,
+                (green_pixels, white_pixels),
+                current_image.labels.as_deref(),
+                settings,
+                pore_coloring,
+                comparison,
+                |x, y, pixel| {
+                    let (x, y) = (x / factor, y / factor);
+                    if x >= image.width() || y >= image.height() {
+                        return;
+                    }
+                    match &base {
+                        Some(base) => {
+                            let mut blended = *base.get_pixel(x, y);
+                            blend_pixel(&mut blended, pixel);
+                            image.put_pixel(x, y, blended);
+                        }
+                        None => blend_pixel(image.get_pixel_mut(x, y), pixel),
+                    }
+                },
+            );
+
+            self.image_to_display =
+                Some(load_texture_into_ctx(ctx, &DynamicImage::ImageRgba8(image)));
+        }
     }
 }
 
@@ -0,0 +1,404 @@
+use std::{collections::BTreeMap, path::Path};
+
+use rust_xlsxwriter::{
+    workbook::Workbook, worksheet::Worksheet, Chart, ChartType, Format, Table, TableColumn,
+    XlsxError,
+};
+
+use super::{
+    image_data::{ImageData, Pore},
+    images::{format_region, NumberFormats, ResultCell},
+    json_export::export_timestamp,
+    project::PROJECT_VERSION,
+    relink::file_fingerprint,
+};
+
+// the charts refer to the results by the name of the sheet
+pub const RESULTS_SHEET: &str = "Results";
+const SUMMARY_SHEET: &str = "Summary";
+
+// the statistics of the summary sheet, `None` if the image has no value
+type Quantity = (&'static str, fn(&ImageData) -> Option<f64>);
+
+const SUMMARY_QUANTITIES: [Quantity; 4] = [
+    ("Density [%]", |image| image.density),
+    ("Pore Count", |image| {
+        image.pores.as_ref().map(|pores| pores.len() as f64)
+    }),
+    ("Mean NN Distance [px]", |image| {
+        image
+            .spatial_statistics
+            .as_ref()
+            .map(|statistics| statistics.mean_nearest_neighbour_distance)
+    }),
+    ("Clark-Evans Index", |image| {
+        image
+            .spatial_statistics
+            .as_ref()
+            .map(|statistics| statistics.clark_evans_index)
+    }),
+];
+
+// adds a table with a header row and `rows` rows of data
+fn add_table(
+    worksheet: &mut Worksheet,
+    first_row: u32,
+    first_column: u16,
+    headers: &[&str],
+    rows: usize,
+) -> Result<(), XlsxError> {
+    let columns = headers
+        .iter()
+        .map(|header| TableColumn::new().set_header(*header))
+        .collect::<Vec<_>>();
+
+    // a table needs at least one row of data
+    if rows == 0 {
+        worksheet.write_row(first_row, first_column, headers.to_vec())?;
+        return Ok(());
+    }
+
+    let table = Table::new().set_columns(&columns).set_banded_rows(true);
+    worksheet.add_table(
+        first_row,
+        first_column,
+        first_row + rows as u32,
+        first_column + headers.len() as u16 - 1,
+        &table,
+    )?;
+
+    Ok(())
+}
+
+// mean, sample standard deviation, min and max
+fn describe(values: &[f64]) -> [ResultCell; 4] {
+    if values.is_empty() {
+        return [
+            ResultCell::Empty,
+            ResultCell::Empty,
+            ResultCell::Empty,
+            ResultCell::Empty,
+        ];
+    }
+
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let std = if values.len() > 1 {
+        ResultCell::Decimal(
+            (values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / (n - 1.0))
+                .sqrt(),
+        )
+    } else {
+        ResultCell::Empty
+    };
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    [
+        ResultCell::Decimal(mean),
+        std,
+        ResultCell::Decimal(min),
+        ResultCell::Decimal(max),
+    ]
+}
+
+// the images are grouped by the folder they are in
+fn group_name(image: &ImageData) -> String {
+    image
+        .path
+        .as_ref()
+        .and_then(|path| path.parent())
+        .and_then(|folder| folder.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// number of pores with an area from 2^i to 2^(i + 1), the sizes span several orders of magnitude
+fn pore_size_distribution(images: &[ImageData]) -> Vec<(String, usize)> {
+    let areas = images
+        .iter()
+        .flat_map(|image| image.pores.iter().flatten())
+        .map(|pore| pore.area.max(1))
+        .collect::<Vec<_>>();
+    let Some(max_area) = areas.iter().max() else {
+        return Vec::new();
+    };
+
+    let mut counts = vec![0; max_area.ilog2() as usize + 1];
+    for area in areas {
+        counts[area.ilog2() as usize] += 1;
+    }
+
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| (format!("{} - {}", 1usize << i, 1usize << (i + 1)), count))
+        .collect()
+}
+
+// the statistics per folder and of all images, the size distribution of the pores and the charts
+pub fn write_summary_sheet(
+    workbook: &mut Workbook,
+    images: &[ImageData],
+    formats: &NumberFormats,
+) -> Result<(), XlsxError> {
+    let worksheet = workbook.add_worksheet().set_name(SUMMARY_SHEET)?;
+
+    let mut folders = BTreeMap::<String, Vec<&ImageData>>::new();
+    for image in images {
+        folders.entry(group_name(image)).or_default().push(image);
+    }
+    let mut groups = folders.into_iter().collect::<Vec<_>>();
+    if groups.len() > 1 {
+        groups.push(("All Images".to_string(), images.iter().collect()));
+    }
+
+    let mut row = 1;
+    for (group, images) in &groups {
+        for (quantity, value) in SUMMARY_QUANTITIES {
+            let values = images
+                .iter()
+                .filter_map(|image| value(image))
+                .collect::<Vec<_>>();
+
+            worksheet.write_string(row, 0, group)?;
+            worksheet.write_string(row, 1, quantity)?;
+            ResultCell::Whole(values.len() as i64).write(worksheet, row, 2, formats)?;
+            for (column, cell) in describe(&values).iter().enumerate() {
+                cell.write(worksheet, row, column as u16 + 3, formats)?;
+            }
+            row += 1;
+        }
+    }
+    add_table(
+        worksheet,
+        0,
+        0,
+        &["Folder", "Quantity", "Images", "Mean", "Std", "Min", "Max"],
+        row as usize - 1,
+    )?;
+
+    let distribution = pore_size_distribution(images);
+    for (i, (areas, count)) in distribution.iter().enumerate() {
+        worksheet.write_string(i as u32 + 1, 8, areas)?;
+        ResultCell::Whole(*count as i64).write(worksheet, i as u32 + 1, 9, formats)?;
+    }
+    add_table(
+        worksheet,
+        0,
+        8,
+        &["Pore Area [px²]", "Pores"],
+        distribution.len(),
+    )?;
+    worksheet.autofit();
+
+    let chart_row = row.max(distribution.len() as u32 + 1) + 2;
+
+    if !images.is_empty() {
+        let last_row = images.len() as u32;
+        let mut chart = Chart::new(ChartType::Column);
+        chart
+            .add_series()
+            .set_categories((RESULTS_SHEET, 1, 0, last_row, 0))
+            .set_values((RESULTS_SHEET, 1, 1, last_row, 1));
+        chart.title().set_name("Density per Image");
+        chart.y_axis().set_name("Density [%]");
+        chart.legend().set_hidden();
+        worksheet.insert_chart(chart_row, 0, &chart)?;
+    }
+
+    if !distribution.is_empty() {
+        let last_row = distribution.len() as u32;
+        let mut chart = Chart::new(ChartType::Column);
+        chart
+            .add_series()
+            .set_categories((SUMMARY_SHEET, 1, 8, last_row, 8))
+            .set_values((SUMMARY_SHEET, 1, 9, last_row, 9));
+        chart.title().set_name("Pore Size Distribution");
+        chart.x_axis().set_name("Pore Area [px²]");
+        chart.y_axis().set_name("Pores");
+        chart.legend().set_hidden();
+        worksheet.insert_chart(chart_row, 8, &chart)?;
+    }
+
+    Ok(())
+}
+
+// where the results come from and the parameters of every image
+pub fn write_parameters_sheet(
+    workbook: &mut Workbook,
+    images: &[ImageData],
+    project_path: Option<&Path>,
+    formats: &NumberFormats,
+) -> Result<(), XlsxError> {
+    let worksheet = workbook.add_worksheet().set_name("Parameters")?;
+    let bold = Format::new().set_bold();
+
+    let project = project_path.map_or("Not saved".to_string(), |path| {
+        path.to_string_lossy().to_string()
+    });
+    let provenance = [
+        ("Program", env!("CARGO_PKG_NAME").to_string()),
+        ("Version", env!("CARGO_PKG_VERSION").to_string()),
+        ("Exported (UTC)", export_timestamp()),
+        ("Project", project),
+        ("Project Version", PROJECT_VERSION.to_string()),
+        // there is no calibration yet
+        ("Units", "Pixels".to_string()),
+    ];
+    for (row, (key, value)) in provenance.iter().enumerate() {
+        worksheet.write_string_with_format(row as u32, 0, *key, &bold)?;
+        worksheet.write_string(row as u32, 1, value)?;
+    }
+
+    let first_row = provenance.len() as u32 + 1;
+    for (i, image) in images.iter().enumerate() {
+        let row = first_row + i as u32 + 1;
+        let path = image.path.as_deref().unwrap_or(Path::new(""));
+        let fingerprint = file_fingerprint(path)
+            .map(|fingerprint| format!("{:016x}", fingerprint))
+            .unwrap_or_default();
+        let (width, height) =
+            image
+                .dimensions
+                .map_or((ResultCell::Empty, ResultCell::Empty), |(width, height)| {
+                    (
+                        ResultCell::Whole(width as i64),
+                        ResultCell::Whole(height as i64),
+                    )
+                });
+        let text = |value: Option<String>| ResultCell::Text(value.unwrap_or_default());
+
+        let cells = [
+            ResultCell::Text(path.to_string_lossy().to_string()),
+            ResultCell::Text(fingerprint),
+            width,
+            height,
+            ResultCell::Whole(image.threshold as i64),
+            ResultCell::Parameter(image.included_min_feature_size),
+            ResultCell::Parameter(image.minimal_pore_size_low),
+            ResultCell::Parameter(image.minimal_pore_size_high),
+            ResultCell::Text(format_region(image)),
+            text(image.reference_line.map(|line| line.to_string())),
+            text(
+                image
+                    .ground_truth_path
+                    .as_ref()
+                    .map(|path| path.to_string_lossy().to_string()),
+            ),
+            ResultCell::Whole(image.manual_overrides.len() as i64),
+            ResultCell::Whole(image.excluded_pores.len() as i64),
+        ];
+        for (column, cell) in cells.iter().enumerate() {
+            cell.write(worksheet, row, column as u16, formats)?;
+        }
+    }
+    add_table(
+        worksheet,
+        first_row,
+        0,
+        &[
+            "File Path",
+            "Fingerprint",
+            "Width [px]",
+            "Height [px]",
+            "Threshold",
+            "Min Feature Size",
+            "Lower Pore Size",
+            "Upper Pore Size",
+            "Selected Region",
+            "Reference Line",
+            "Ground Truth",
+            "Manual Pixels",
+            "Excluded Pores",
+        ],
+        images.len(),
+    )?;
+    worksheet.autofit();
+
+    Ok(())
+}
+
+// sheet names have at most 31 characters, must be unique and can't contain some characters
+fn pore_sheet_name(image: &ImageData, used: &mut Vec<String>) -> String {
+    let stem = image
+        .path
+        .as_ref()
+        .and_then(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = stem
+        .chars()
+        .map(|c| if "[]:*?/\\'".contains(c) { '_' } else { c })
+        .take(20)
+        .collect::<String>();
+
+    let mut name = format!("Pores {}", stem.trim());
+    let mut i = 2;
+    while used.iter().any(|used| used.eq_ignore_ascii_case(&name)) {
+        name = format!("Pores {} ({})", stem.trim(), i);
+        i += 1;
+    }
+    used.push(name.clone());
+
+    name
+}
+
+// every pore of each analyzed image, the labels match the JSON export
+pub fn write_pore_sheets(
+    workbook: &mut Workbook,
+    images: &[ImageData],
+    formats: &NumberFormats,
+) -> Result<(), XlsxError> {
+    let mut used_names = Vec::new();
+
+    for image in images {
+        let Some(pores) = &image.pores else {
+            continue;
+        };
+        let name = pore_sheet_name(image, &mut used_names);
+        let worksheet = workbook.add_worksheet().set_name(name)?;
+
+        let excluded = image.matched_excluded_pores.iter().flatten();
+        let rows = pores
+            .iter()
+            .map(|pore| (pore, false))
+            .chain(excluded.map(|pore| (pore, true)))
+            .collect::<Vec<(&Pore, bool)>>();
+
+        for (i, (pore, excluded)) in rows.iter().enumerate() {
+            let row = i as u32 + 1;
+            let cells = [
+                ResultCell::Whole(pore.label as i64),
+                ResultCell::Whole(pore.area as i64),
+                ResultCell::Decimal(pore.centroid.x),
+                ResultCell::Decimal(pore.centroid.y),
+                ResultCell::Text(if *excluded { "Yes" } else { "No" }.to_string()),
+            ];
+            for (column, cell) in cells.iter().enumerate() {
+                cell.write(worksheet, row, column as u16, formats)?;
+            }
+        }
+        add_table(
+            worksheet,
+            0,
+            0,
+            &[
+                "Label",
+                "Area [px²]",
+                "Centroid X [px]",
+                "Centroid Y [px]",
+                "Excluded",
+            ],
+            rows.len(),
+        )?;
+        worksheet.set_freeze_panes(1, 0)?;
+        worksheet.autofit();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,656 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use calamine::{open_workbook, Data, DataType, Range, Reader, Xlsx};
+
+use super::{
+    excluded_pores::parse_excluded_pores,
+    manual_mask::ManualOverride,
+    project::{Project, ProjectImage},
+    relink::resolve_path,
+};
+
+// the columns of the results written by `Images::export`, they are matched by their header
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Column {
+    FilePath,
+    FileName,
+    Density,
+    Threshold,
+    MinFeatureSize,
+    LowerPoreSize,
+    UpperPoreSize,
+    SelectedRegion,
+    ExcludedPores,
+}
+
+// exported columns that are computed again after loading
+const IGNORED_COLUMNS: [&str; 7] = [
+    "porecount",
+    "meannndistance",
+    "meanspacing",
+    "clarkevansindex",
+    "densityuncertainty",
+    "uncertaintygreylevels",
+    "manualpixels",
+];
+
+// ignores case, spaces and units, e.g. "Lower Pore Size [px]" matches "lowerporesizepx"
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl Column {
+    fn from_header(header: &str) -> Option<Self> {
+        match normalize_header(header).trim_end_matches("px") {
+            "filepath" | "path" => Some(Self::FilePath),
+            "filename" | "file" | "image" => Some(Self::FileName),
+            "density" => Some(Self::Density),
+            "threshold" => Some(Self::Threshold),
+            "minfeaturesize" | "minimalfeaturesize" => Some(Self::MinFeatureSize),
+            "lowerporesize" | "minimalporesizelow" => Some(Self::LowerPoreSize),
+            "upperporesize" | "minimalporesizehigh" => Some(Self::UpperPoreSize),
+            "selectedregion" | "region" => Some(Self::SelectedRegion),
+            "excludedpores" => Some(Self::ExcludedPores),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::FilePath => "File Path",
+            Self::FileName => "Filename",
+            Self::Density => "Density",
+            Self::Threshold => "Threshold",
+            Self::MinFeatureSize => "Min Feature Size",
+            Self::LowerPoreSize => "Lower Pore Size",
+            Self::UpperPoreSize => "Upper Pore Size",
+            Self::SelectedRegion => "Selected Region",
+            Self::ExcludedPores => "Excluded Pores",
+        }
+    }
+}
+
+pub struct ExcelImport {
+    pub project: Project,
+    // everything that was skipped or could not be read, shown after the import
+    pub problems: Vec<String>,
+}
+
+// with a decimal point or the decimal comma of the `Comma` export
+fn parse_decimal(text: &str) -> Option<f64> {
+    let text = text.trim();
+    text.parse()
+        .ok()
+        .or_else(|| text.replace(',', ".").parse().ok())
+}
+
+// numbers may be stored as numbers or as text
+fn parse_number(cell: &Data) -> Result<f64, String> {
+    match cell {
+        Data::Int(_) | Data::Float(_) => Ok(cell.as_f64().unwrap()),
+        Data::String(text) => {
+            parse_decimal(text).ok_or(format!("\"{}\" is not a number", text.trim()))
+        }
+        cell => Err(format!("\"{}\" is not a number", cell)),
+    }
+}
+
+fn parse_whole_number(cell: &Data, max: f64) -> Result<f64, String> {
+    let number = parse_number(cell)?;
+    if number.fract() != 0.0 || !(0.0..=max).contains(&number) {
+        return Err(format!(
+            "{} is not a whole number from 0 to {}",
+            number, max
+        ));
+    }
+
+    Ok(number)
+}
+
+// start and end point
+type Region = ([f64; 2], [f64; 2]);
+
+// "(x, y) - (x, y)", an empty region or "No Region" means the whole image
+fn parse_region(text: &str) -> Result<Option<Region>, String> {
+    let text = text.trim();
+    if text.is_empty() || text.eq_ignore_ascii_case("no region") {
+        return Ok(None);
+    }
+
+    let points = text
+        .split(" - ")
+        .map(|point| {
+            let coordinates = point
+                .trim()
+                .trim_start_matches('(')
+                .trim_end_matches(')')
+                .split(", ")
+                .map(parse_decimal)
+                .collect::<Option<Vec<_>>>()?;
+            match coordinates[..] {
+                [x, y] => Some([x, y]),
+                _ => None,
+            }
+        })
+        .collect::<Option<Vec<_>>>();
+
+    match points.as_deref() {
+        // the export writes an empty region for images without one
+        Some([start, end]) if start == end => Ok(None),
+        Some([start, end]) => Ok(Some((*start, *end))),
+        _ => Err(format!("\"{}\" is not a region like (x, y) - (x, y)", text)),
+    }
+}
+
+fn headers(range: &Range<Data>) -> Vec<String> {
+    range
+        .rows()
+        .next()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .unwrap_or_default()
+}
+
+// the row number shown in Excel
+fn excel_row(range: &Range<Data>, index: usize) -> usize {
+    range.start().map(|(row, _)| row as usize).unwrap_or(0) + index + 1
+}
+
+struct RowReader<'a> {
+    row: &'a [Data],
+    columns: &'a HashMap<Column, usize>,
+    // all errors of the row are reported at once
+    errors: Vec<String>,
+}
+
+impl<'a> RowReader<'a> {
+    fn cell(&self, column: Column) -> Option<&'a Data> {
+        self.columns
+            .get(&column)
+            .and_then(|i| self.row.get(*i))
+            .filter(|cell| !cell.is_empty())
+    }
+
+    fn read<T>(&mut self, column: Column, parse: impl Fn(&Data) -> Result<T, String>) -> Option<T> {
+        match parse(self.cell(column)?) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.errors.push(format!("{}: {}", column.name(), err));
+                None
+            }
+        }
+    }
+}
+
+// reads one image from every row, `base` returns the image the parameters of the row are applied to,
+// so only the columns that are there change it
+pub fn read_images(
+    sheet: &str,
+    range: &Range<Data>,
+    folder: &Path,
+    problems: &mut Vec<String>,
+    mut base: impl FnMut(PathBuf) -> Result<ProjectImage, String>,
+) -> Vec<ProjectImage> {
+    let mut columns = HashMap::new();
+    for (i, header) in headers(range).iter().enumerate() {
+        match Column::from_header(header) {
+            Some(column) => {
+                columns.entry(column).or_insert(i);
+            }
+            None if header.trim().is_empty()
+                || IGNORED_COLUMNS.contains(&normalize_header(header).as_str()) => {}
+            None => problems.push(format!(
+                "{}: unknown column \"{}\" is ignored",
+                sheet,
+                header.trim()
+            )),
+        }
+    }
+
+    let mut images = Vec::new();
+    let mut rows = 0;
+
+    for (i, row) in range.rows().enumerate().skip(1) {
+        // empty rows and the total row of the table are skipped
+        let is_total = row
+            .first()
+            .is_some_and(|cell| cell.to_string().trim().eq_ignore_ascii_case("total"));
+        if row.iter().all(|cell| cell.is_empty()) || is_total {
+            continue;
+        }
+        rows += 1;
+
+        let mut reader = RowReader {
+            row,
+            columns: &columns,
+            errors: Vec::new(),
+        };
+        let location = format!("{} row {}", sheet, excel_row(range, i));
+        // without a path the file is looked for next to the table
+        let image_path = match (reader.cell(Column::FilePath), reader.cell(Column::FileName)) {
+            (Some(path), _) => resolve_path(folder, Path::new(path.to_string().trim())),
+            (None, Some(name)) => folder.join(name.to_string().trim()),
+            (None, None) => {
+                problems.push(format!("{}: no file path, the row is skipped", location));
+                continue;
+            }
+        };
+        let mut image = match base(image_path) {
+            Ok(image) => image,
+            Err(err) => {
+                problems.push(format!("{}: {}, the row is skipped", location, err));
+                continue;
+            }
+        };
+
+        if let Some(density) = reader.read(Column::Density, parse_number) {
+            image.density = Some(density);
+        }
+        if let Some(threshold) =
+            reader.read(Column::Threshold, |cell| parse_whole_number(cell, 255.0))
+        {
+            image.threshold = threshold as i16;
+        }
+        if let Some(size) = reader.read(Column::MinFeatureSize, parse_number) {
+            image.included_min_feature_size = size as f32;
+        }
+        if let Some(size) = reader.read(Column::LowerPoreSize, parse_number) {
+            image.minimal_pore_size_low = size as f32;
+        }
+        if let Some(size) = reader.read(Column::UpperPoreSize, parse_number) {
+            image.minimal_pore_size_high = size as f32;
+        }
+        if let Some(region) = reader.read(Column::SelectedRegion, |cell| {
+            parse_region(&cell.to_string())
+        }) {
+            image.region_start = region.map(|(start, _)| start);
+            image.region_end = region.map(|(_, end)| end);
+        }
+        if let Some(excluded_pores) = reader.read(Column::ExcludedPores, |cell| {
+            parse_excluded_pores(&cell.to_string())
+        }) {
+            image.excluded_pores = excluded_pores;
+        }
+
+        if reader.errors.is_empty() {
+            images.push(image);
+        } else {
+            problems.push(format!(
+                "{}: {}, the row is skipped",
+                location,
+                reader.errors.join(", ")
+            ));
+        }
+    }
+
+    if images.len() < rows {
+        problems.insert(
+            0,
+            format!("{} of {} rows were imported", images.len(), rows),
+        );
+    }
+
+    images
+}
+
+pub fn import_excel(path: &Path) -> Result<ExcelImport, String> {
+    let mut workbook: Xlsx<_> =
+        open_workbook(path).map_err(|err| format!("Could not open {:?}: {}", path, err))?;
+    let worksheets = workbook.worksheets();
+    let folder = path.parent().unwrap_or(Path::new(""));
+
+    // the results are on the sheet with the most known columns, its name depends on the Excel version
+    let (_, (sheet, range)) = worksheets
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, range))| {
+            headers(range).iter().any(|header| {
+                matches!(
+                    Column::from_header(header),
+                    Some(Column::FilePath | Column::FileName)
+                )
+            })
+        })
+        .max_by_key(|(i, (_, range))| {
+            let known = headers(range)
+                .iter()
+                .filter(|header| Column::from_header(header).is_some())
+                .count();
+            // the first sheet wins a tie
+            (known, std::cmp::Reverse(*i))
+        })
+        .ok_or(format!(
+            "{:?} has no sheet with a \"File Path\" or \"Filename\" column",
+            path
+        ))?;
+
+    let mut problems = Vec::new();
+    let mut project = Project {
+        images: read_images(sheet, range, folder, &mut problems, |path| {
+            Ok(ProjectImage {
+                path,
+                ..Default::default()
+            })
+        }),
+        selected: Some(0),
+        ..Default::default()
+    };
+
+    for image in &project.images {
+        if !image.path.is_file() {
+            problems.push(format!("{:?} not found, it can be relinked", image.path));
+        }
+    }
+
+    import_manual_overrides(&worksheets, folder, &mut project, &mut problems);
+
+    Ok(ExcelImport { project, problems })
+}
+
+// manual edits, older exports don't have this sheet
+fn import_manual_overrides(
+    worksheets: &[(String, Range<Data>)],
+    folder: &Path,
+    project: &mut Project,
+    problems: &mut Vec<String>,
+) {
+    let Some((sheet, range)) = worksheets
+        .iter()
+        .find(|(sheet, _)| normalize_header(sheet) == "manualoverrides")
+    else {
+        return;
+    };
+
+    let headers = headers(range)
+        .iter()
+        .map(|header| normalize_header(header))
+        .collect::<Vec<_>>();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let (
+        Some(path_column),
+        Some(x_column),
+        Some(start_column),
+        Some(end_column),
+        Some(value_column),
+    ) = (
+        column("filepath"),
+        column("x"),
+        column("ystart"),
+        column("yend"),
+        column("override"),
+    )
+    else {
+        problems.push(format!(
+            "{}: the columns File Path, X, Y Start, Y End and Override are needed, the manual edits are skipped",
+            sheet
+        ));
+        return;
+    };
+
+    let mut unknown_paths = Vec::new();
+    for (i, row) in range.rows().enumerate().skip(1) {
+        if row.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+        let location = format!("{} row {}", sheet, excel_row(range, i));
+        let cell = |column: usize| row.get(column).cloned().unwrap_or_default();
+
+        let number = |column: usize, name: &str| {
+            parse_whole_number(&cell(column), u32::MAX as f64)
+                .map(|number| number as u32)
+                .map_err(|err| format!("{}: {}", name, err))
+        };
+        let run = (|| {
+            let value: ManualOverride = cell(value_column).to_string().trim().parse()?;
+            Ok::<_, String>((
+                number(x_column, "X")?,
+                number(start_column, "Y Start")?,
+                number(end_column, "Y End")?,
+                value,
+            ))
+        })();
+        let run = match run {
+            Ok((_, y_start, y_end, _)) if y_end < y_start => {
+                problems.push(format!(
+                    "{}: Y End {} is before Y Start {}, the row is skipped",
+                    location, y_end, y_start
+                ));
+                continue;
+            }
+            Ok(run) => run,
+            Err(err) => {
+                problems.push(format!("{}: {}, the row is skipped", location, err));
+                continue;
+            }
+        };
+
+        let path = resolve_path(folder, Path::new(cell(path_column).to_string().trim()));
+        match project.images.iter_mut().find(|image| image.path == path) {
+            Some(image) => image.manual_overrides.push(run),
+            None if !unknown_paths.contains(&path) => {
+                problems.push(format!(
+                    "{}: {:?} is not in the results, its manual edits are skipped",
+                    location, path
+                ));
+                unknown_paths.push(path);
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::excluded_pores::ExcludedPore;
+    use egui_plot::PlotPoint;
+
+    // numbers are written as numbers, everything else as text
+    fn range(rows: &[&[&str]]) -> Range<Data> {
+        let width = rows.iter().map(|row| row.len()).max().unwrap();
+        let mut range = Range::new((0, 0), (rows.len() as u32 - 1, width as u32 - 1));
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let value = match cell.parse::<f64>() {
+                    _ if cell.is_empty() => continue,
+                    Ok(number) => Data::Float(number),
+                    Err(_) => Data::String(cell.to_string()),
+                };
+                range.set_value((y as u32, x as u32), value);
+            }
+        }
+
+        range
+    }
+
+    fn read(rows: &[&[&str]]) -> (Vec<ProjectImage>, Vec<String>) {
+        let mut problems = Vec::new();
+        let images = read_images(
+            "Results",
+            &range(rows),
+            Path::new("/results"),
+            &mut problems,
+            |path| match path.file_name().unwrap().to_str() {
+                Some("unknown.png") => Err("not loaded".to_string()),
+                _ => Ok(ProjectImage {
+                    path,
+                    ..Default::default()
+                }),
+            },
+        );
+
+        (images, problems)
+    }
+
+    #[test]
+    fn columns_are_matched_by_their_header() {
+        let (images, problems) = read(&[
+            &[
+                "Upper Pore Size [px]",
+                "Pore Count",
+                "threshold",
+                "Comment",
+                "File Path",
+                "Lower Pore Size [px]",
+                "Excluded Pores",
+            ],
+            &[
+                "500",
+                "12",
+                "80",
+                "ok",
+                "images/a.png",
+                "",
+                "(1, 2, 30); (4.5, 6, 70)",
+            ],
+        ]);
+
+        assert_eq!(
+            problems,
+            vec!["Results: unknown column \"Comment\" is ignored"]
+        );
+        let default = ProjectImage::default();
+        let image = &images[0];
+        assert_eq!(image.path, Path::new("/results/images/a.png"));
+        assert_eq!(image.threshold, 80);
+        assert_eq!(image.minimal_pore_size_high, 500.0);
+        // empty cells keep the parameter of the image
+        assert_eq!(image.minimal_pore_size_low, default.minimal_pore_size_low);
+        assert_eq!(
+            image.excluded_pores,
+            vec![
+                ExcludedPore {
+                    centroid: PlotPoint::new(1.0, 2.0),
+                    area: 30
+                },
+                ExcludedPore {
+                    centroid: PlotPoint::new(4.5, 6.0),
+                    area: 70
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn comma_decimals_and_the_total_row() {
+        let (images, problems) = read(&[
+            &["Filename", "Density", "Min Feature Size", "Selected Region"],
+            &["a.png", "12,5", "2,25", "(10,5, 20) - (30, 40,75)"],
+            &["", "", "", ""],
+            &["b.png", "7.5", "3", "No Region"],
+            &["Total", "10", "", ""],
+        ]);
+
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].path, Path::new("/results/a.png"));
+        assert_eq!(images[0].density, Some(12.5));
+        assert_eq!(images[0].included_min_feature_size, 2.25);
+        assert_eq!(images[0].region_start, Some([10.5, 20.0]));
+        assert_eq!(images[0].region_end, Some([30.0, 40.75]));
+        assert_eq!(images[1].density, Some(7.5));
+        assert_eq!(images[1].region_start, None);
+    }
+
+    #[test]
+    fn rows_with_errors_are_skipped() {
+        let (images, problems) = read(&[
+            &["File Path", "Threshold", "Density", "Selected Region"],
+            &["a.png", "300", "many", ""],
+            &["b.png", "12.5", "", ""],
+            &["c.png", "", "", "(1, 2)"],
+            &["", "80", "", ""],
+            &["unknown.png", "80", "", ""],
+            &["d.png", "80", "", ""],
+        ]);
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].path, Path::new("/results/d.png"));
+        assert_eq!(
+            problems,
+            vec![
+                "1 of 6 rows were imported",
+                "Results row 2: Density: \"many\" is not a number, Threshold: 300 is not a whole number from 0 to 255, the row is skipped",
+                "Results row 3: Threshold: 12.5 is not a whole number from 0 to 255, the row is skipped",
+                "Results row 4: Selected Region: \"(1, 2)\" is not a region like (x, y) - (x, y), the row is skipped",
+                "Results row 5: no file path, the row is skipped",
+                "Results row 6: not loaded, the row is skipped",
+            ]
+        );
+    }
+
+    #[test]
+    fn manual_overrides_are_imported_from_their_sheet() {
+        let folder = std::env::temp_dir().join(format!(
+            "pore_detection_excel_import_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("results.xlsx");
+
+        let mut workbook = rust_xlsxwriter::Workbook::new();
+        let rows: [&[&str]; 3] = [
+            &["File Path", "Density"],
+            &["a.png", "10"],
+            &["b.png", "20"],
+        ];
+        let sheet = workbook.add_worksheet();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                sheet.write(y as u32, x as u16, *cell).unwrap();
+            }
+        }
+        let rows: [&[&str]; 5] = [
+            &["File Path", "X", "Y Start", "Y End", "Override"],
+            &["a.png", "1", "2", "4", "Pore"],
+            &["a.png", "1", "5", "3", "Material"],
+            &["c.png", "0", "0", "0", "Pore"],
+            &["c.png", "0", "1", "1", "Pore"],
+        ];
+        let sheet = workbook
+            .add_worksheet()
+            .set_name("Manual Overrides")
+            .unwrap();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                sheet.write(y as u32, x as u16, *cell).unwrap();
+            }
+        }
+        workbook.save(&path).unwrap();
+
+        let import = import_excel(&path);
+        std::fs::remove_dir_all(&folder).unwrap();
+        let ExcelImport { project, problems } = import.ok().unwrap();
+
+        assert_eq!(project.images.len(), 2);
+        assert_eq!(project.images[0].path, folder.join("a.png"));
+        assert_eq!(project.images[0].density, Some(10.0));
+        assert_eq!(
+            project.images[0].manual_overrides,
+            vec![(1, 2, 4, ManualOverride::Pore)]
+        );
+        assert!(project.images[1].manual_overrides.is_empty());
+
+        let problems = problems
+            .iter()
+            .filter(|problem| !problem.ends_with("not found, it can be relinked"))
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            vec![
+                "Manual Overrides row 3: Y End 3 is before Y Start 5, the row is skipped".to_string(),
+                format!(
+                    "Manual Overrides row 4: {:?} is not in the results, its manual edits are skipped",
+                    folder.join("c.png")
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use core::fmt;
+use std::str::FromStr;
+
+use egui_plot::PlotPoint;
+
+use super::image_data::Pore;
+
+// how far the centroid may move and how much the area may change before an exclusion no longer matches
+const MAX_CENTROID_DISTANCE: f64 = 3.0;
+const MAX_AREA_CHANGE: f64 = 0.2;
+
+// pores are identified by centroid and area because the labels change with every analysis
+#[derive(Clone, Copy, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+pub struct ExcludedPore {
+    #[serde(with = "super::project::plot_point")]
+    pub centroid: PlotPoint,
+    pub area: usize,
+}
+
+impl ExcludedPore {
+    pub fn from_pore(pore: &Pore) -> Self {
+        Self {
+            centroid: pore.centroid,
+            area: pore.area,
+        }
+    }
+
+    pub fn matches(&self, pore: &Pore) -> bool {
+        let distance = ((self.centroid.x - pore.centroid.x).powi(2)
+            + (self.centroid.y - pore.centroid.y).powi(2))
+        .sqrt();
+        let area_change =
+            (self.area as f64 - pore.area as f64).abs() / self.area.max(pore.area).max(1) as f64;
+
+        distance <= MAX_CENTROID_DISTANCE && area_change <= MAX_AREA_CHANGE
+    }
+}
+
+impl fmt::Display for ExcludedPore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "({:.2}, {:.2}, {})",
+            self.centroid.x, self.centroid.y, self.area
+        )
+    }
+}
+
+impl FromStr for ExcludedPore {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .split(", ")
+            .collect::<Vec<_>>();
+
+        if values.len() != 3 {
+            return Err(format!("invalid excluded pore: {}", s));
+        }
+
+        let parse_error = |_| format!("invalid excluded pore: {}", s);
+        Ok(Self {
+            centroid: PlotPoint::new(
+                values[0].parse::<f64>().map_err(parse_error)?,
+                values[1].parse::<f64>().map_err(parse_error)?,
+            ),
+            area: values[2]
+                .parse()
+                .map_err(|_| format!("invalid excluded pore: {}", s))?,
+        })
+    }
+}
+
+pub fn format_excluded_pores(excluded_pores: &[ExcludedPore]) -> String {
+    excluded_pores
+        .iter()
+        .map(|excluded_pore| excluded_pore.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+pub fn parse_excluded_pores(s: &str) -> Result<Vec<ExcludedPore>, String> {
+    s.split(';')
+        .filter(|part| !part.trim().is_empty())
+        .map(|part| part.parse())
+        .collect()
+}
@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use egui_plot::PlotPoint;
+use image::{GrayImage, Luma};
+
+use super::pixel_mask::PixelMask;
+
+#[derive(Clone, Debug, Default)]
+pub struct SegmentationMetrics {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub true_negatives: usize,
+}
+
+impl SegmentationMetrics {
+    fn ratio(numerator: usize, denominator: usize) -> Option<f64> {
+        if denominator == 0 {
+            None
+        } else {
+            Some(numerator as f64 / denominator as f64)
+        }
+    }
+
+    pub fn precision(&self) -> Option<f64> {
+        Self::ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    pub fn recall(&self) -> Option<f64> {
+        Self::ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    pub fn iou(&self) -> Option<f64> {
+        Self::ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives + self.false_negatives,
+        )
+    }
+
+    pub fn dice(&self) -> Option<f64> {
+        Self::ratio(
+            2 * self.true_positives,
+            2 * self.true_positives + self.false_positives + self.false_negatives,
+        )
+    }
+
+    fn evaluated_pixels(&self) -> usize {
+        self.true_positives + self.false_positives + self.false_negatives + self.true_negatives
+    }
+
+    // share of pore pixels in all evaluated pixels in percent, unlike the density (pores / material)
+    // the ground truth has no separate class for the area that is neither pore nor material
+    pub fn segmented_pore_fraction(&self) -> Option<f64> {
+        Self::ratio(
+            self.true_positives + self.false_positives,
+            self.evaluated_pixels(),
+        )
+        .map(|ratio| ratio * 100.0)
+    }
+
+    pub fn ground_truth_pore_fraction(&self) -> Option<f64> {
+        Self::ratio(
+            self.true_positives + self.false_negatives,
+            self.evaluated_pixels(),
+        )
+        .map(|ratio| ratio * 100.0)
+    }
+
+    // absolute difference in percentage points
+    pub fn pore_fraction_error(&self) -> Option<f64> {
+        Some((self.segmented_pore_fraction()? - self.ground_truth_pore_fraction()?).abs())
+    }
+}
+
+#[derive(Clone)]
+pub struct SegmentationComparison {
+    pub metrics: SegmentationMetrics,
+    pub false_positive_pixels: PixelMask,
+    pub false_negative_pixels: PixelMask,
+}
+
+// white (or any bright value) in the mask marks a pore, it has to have the same size as the image
+pub fn load_ground_truth(path: &Path, (width, height): (u32, u32)) -> Result<GrayImage, String> {
+    let (mask_width, mask_height) = image::image_dimensions(path).map_err(|err| err.to_string())?;
+    if (mask_width, mask_height) != (width, height) {
+        return Err(format!(
+            "the mask has {} × {} pixels but the image {} × {}",
+            mask_width, mask_height, width, height
+        ));
+    }
+
+    let mut mask = image::open(path).map_err(|err| err.to_string())?.to_luma8();
+    mask.pixels_mut().for_each(|p| {
+        *p = if p[0] > 127 { Luma([255]) } else { Luma([0]) };
+    });
+
+    Ok(mask)
+}
+
+// `None` if the mask does not have the size of the segmentation
+pub fn compare_segmentation(
+    ground_truth: &GrayImage,
+    green_pixels: &PixelMask,
+    region: Option<(PlotPoint, PlotPoint)>,
+) -> Option<SegmentationComparison> {
+    let (width, height) = ground_truth.dimensions();
+    if (width, height) != (green_pixels.width(), green_pixels.height()) {
+        return None;
+    }
+
+    // same region bounds as in the analysis
+    let (x_start, x_end, y_start, y_end) = match region {
+        Some((region_start, region_end)) => (
+            region_start.x as u32,
+            region_end.x as u32,
+            height - region_start.y as u32,
+            height - region_end.y as u32,
+        ),
+        None => (0, width - 1, 0, height - 1),
+    };
+
+    let mut comparison = SegmentationComparison {
+        metrics: SegmentationMetrics::default(),
+        false_positive_pixels: PixelMask::new(width, height),
+        false_negative_pixels: PixelMask::new(width, height),
+    };
+
+    for y in y_start..=y_end.min(height - 1) {
+        for x in x_start..=x_end.min(width - 1) {
+            let is_segmented = green_pixels.get(x, y);
+            let is_ground_truth = ground_truth.get_pixel(x, y)[0] > 0;
+
+            match (is_segmented, is_ground_truth) {
+                (true, true) => comparison.metrics.true_positives += 1,
+                (true, false) => {
+                    comparison.metrics.false_positives += 1;
+                    comparison.false_positive_pixels.set(x, y, true);
+                }
+                (false, true) => {
+                    comparison.metrics.false_negatives += 1;
+                    comparison.false_negative_pixels.set(x, y, true);
+                }
+                (false, false) => comparison.metrics.true_negatives += 1,
+            }
+        }
+    }
+
+    Some(comparison)
+}
@@ -0,0 +1,210 @@
+use core::fmt;
+
+use image::GrayImage;
+
+use super::tiled::StripSource;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AutoThreshold {
+    Otsu,
+    IsoData,
+    Triangle,
+}
+
+impl AutoThreshold {
+    pub const ALL: [AutoThreshold; 3] = [
+        AutoThreshold::Otsu,
+        AutoThreshold::IsoData,
+        AutoThreshold::Triangle,
+    ];
+}
+
+impl fmt::Display for AutoThreshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AutoThreshold::Otsu => write!(f, "Otsu"),
+            AutoThreshold::IsoData => write!(f, "IsoData"),
+            AutoThreshold::Triangle => write!(f, "Triangle"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GreyHistogram {
+    // region in image coordinates (x start, x end, y start, y end) the histogram was computed for
+    pub region: Option<(u32, u32, u32, u32)>,
+    pub counts: [usize; 256],
+}
+
+impl GreyHistogram {
+    pub fn new(grayscale: &GrayImage, region: Option<(u32, u32, u32, u32)>) -> Self {
+        let (width, height) = grayscale.dimensions();
+        let (x_start, x_end, y_start, y_end) =
+            region.unwrap_or((0, width.saturating_sub(1), 0, height.saturating_sub(1)));
+
+        let mut counts = [0; 256];
+        for y in y_start..=y_end.min(height.saturating_sub(1)) {
+            for x in x_start..=x_end.min(width.saturating_sub(1)) {
+                counts[grayscale.get_pixel(x, y)[0] as usize] += 1;
+            }
+        }
+
+        Self { region, counts }
+    }
+
+    // same counts as `new` on the whole image, only one strip is in memory at a time
+    pub fn from_strips(
+        source: &mut impl StripSource,
+        region: Option<(u32, u32, u32, u32)>,
+    ) -> Result<Self, String> {
+        let (width, height) = source.dimensions();
+        let (x_start, x_end, y_start, y_end) =
+            region.unwrap_or((0, width.saturating_sub(1), 0, height.saturating_sub(1)));
+
+        let mut counts = [0; 256];
+        let mut strip_y = 0;
+        for index in 0..source.strip_count() {
+            let strip = source.read_strip(index)?;
+            for y in y_start.max(strip_y)..=y_end.min(strip_y + strip.height() - 1) {
+                for x in x_start..=x_end.min(width.saturating_sub(1)) {
+                    counts[strip.get_pixel(x, y - strip_y)[0] as usize] += 1;
+                }
+            }
+            strip_y += strip.height();
+        }
+
+        Ok(Self { region, counts })
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    // share of pixels at or below the threshold (the pore candidates) in percent
+    pub fn pore_share(&self, threshold: i16) -> Option<f64> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+
+        let below = self.counts[..=(threshold.clamp(0, 255) as usize)]
+            .iter()
+            .sum::<usize>();
+        Some(below as f64 / total as f64 * 100.0)
+    }
+
+    // the returned level is the last grey value that still counts as pore, like the threshold slider
+    pub fn auto_threshold(&self, method: AutoThreshold) -> Option<i16> {
+        if self.total() == 0 {
+            return None;
+        }
+
+        match method {
+            AutoThreshold::Otsu => Some(self.otsu()),
+            AutoThreshold::IsoData => Some(self.iso_data()),
+            AutoThreshold::Triangle => Some(self.triangle()),
+        }
+    }
+
+    fn otsu(&self) -> i16 {
+        let total = self.total() as f64;
+        let sum_total = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(level, count)| level as f64 * *count as f64)
+            .sum::<f64>();
+
+        let (mut weight_low, mut sum_low) = (0.0, 0.0);
+        let (mut best_level, mut best_variance) = (0, 0.0);
+
+        for (level, count) in self.counts.iter().enumerate() {
+            weight_low += *count as f64;
+            sum_low += level as f64 * *count as f64;
+
+            let weight_high = total - weight_low;
+            if weight_low == 0.0 || weight_high == 0.0 {
+                continue;
+            }
+
+            let mean_low = sum_low / weight_low;
+            let mean_high = (sum_total - sum_low) / weight_high;
+            let variance = weight_low * weight_high * (mean_low - mean_high).powi(2);
+
+            if variance > best_variance {
+                best_variance = variance;
+                best_level = level;
+            }
+        }
+
+        best_level as i16
+    }
+
+    // Ridler-Calvard: the threshold is moved to the middle of both class means until it is stable
+    fn iso_data(&self) -> i16 {
+        let class_mean = |levels: std::ops::Range<usize>| {
+            let count = self.counts[levels.clone()].iter().sum::<usize>();
+            if count == 0 {
+                return None;
+            }
+            let sum = levels
+                .map(|level| level as f64 * self.counts[level] as f64)
+                .sum::<f64>();
+            Some(sum / count as f64)
+        };
+
+        let mut threshold = class_mean(0..256).unwrap_or(127.0) as usize;
+        for _ in 0..256 {
+            let mean_low = class_mean(0..threshold + 1).unwrap_or(0.0);
+            let mean_high = class_mean(threshold + 1..256).unwrap_or(255.0);
+            let next = ((mean_low + mean_high) / 2.0) as usize;
+
+            if next == threshold {
+                break;
+            }
+            threshold = next;
+        }
+
+        threshold as i16
+    }
+
+    // largest distance between the histogram and the line from its peak to the far end of the longer tail
+    fn triangle(&self) -> i16 {
+        let first = self.counts.iter().position(|count| *count > 0).unwrap_or(0);
+        let last = self
+            .counts
+            .iter()
+            .rposition(|count| *count > 0)
+            .unwrap_or(255);
+        let peak = (0..256).max_by_key(|level| self.counts[*level]).unwrap();
+
+        let end = if peak - first > last - peak {
+            first
+        } else {
+            last
+        };
+        if end == peak {
+            return peak as i16;
+        }
+
+        let (peak_x, peak_y) = (peak as f64, self.counts[peak] as f64);
+        let (end_x, end_y) = (end as f64, self.counts[end] as f64);
+        let length = ((end_x - peak_x).powi(2) + (end_y - peak_y).powi(2)).sqrt();
+
+        let (low, high) = (peak.min(end), peak.max(end));
+        let level = (low..=high)
+            .max_by(|a, b| {
+                let distance = |level: usize| {
+                    ((end_y - peak_y) * level as f64 - (end_x - peak_x) * self.counts[level] as f64
+                        + end_x * peak_y
+                        - end_y * peak_x)
+                        .abs()
+                        / length
+                };
+                distance(*a).total_cmp(&distance(*b))
+            })
+            .unwrap();
+
+        level as i16
+    }
+}
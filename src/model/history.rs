@@ -0,0 +1,429 @@
+use egui_plot::PlotPoint;
+
+use super::{
+    depth_profile::ReferenceLine,
+    detection_app::PoreDetectionApp,
+    excluded_pores::ExcludedPore,
+    image_data::ImageData,
+    manual_mask::{ManualOverride, ManualOverrides},
+    project::ProjectImage,
+};
+
+// the oldest entries are dropped
+const HISTORY_LIMIT: usize = 200;
+
+// the parameters of an image that can be undone
+#[derive(Clone, PartialEq)]
+pub struct ImageState {
+    pub threshold: i16,
+    pub minimal_pore_size_low: f32,
+    pub minimal_pore_size_high: f32,
+    pub included_min_feature_size: f32,
+    pub region_start: Option<PlotPoint>,
+    pub region_end: Option<PlotPoint>,
+    pub reference_line: Option<ReferenceLine>,
+    pub excluded_pores: Vec<ExcludedPore>,
+}
+
+impl ImageState {
+    pub fn of(image: &ImageData) -> Self {
+        Self {
+            threshold: image.threshold,
+            minimal_pore_size_low: image.minimal_pore_size_low,
+            minimal_pore_size_high: image.minimal_pore_size_high,
+            included_min_feature_size: image.included_min_feature_size,
+            region_start: image.region_start,
+            region_end: image.region_end,
+            reference_line: image.reference_line,
+            excluded_pores: image.excluded_pores.clone(),
+        }
+    }
+
+    fn apply(&self, image: &mut ImageData) {
+        image.threshold = self.threshold;
+        image.minimal_pore_size_low = self.minimal_pore_size_low;
+        image.minimal_pore_size_high = self.minimal_pore_size_high;
+        image.included_min_feature_size = self.included_min_feature_size;
+        image.region_start = self.region_start;
+        image.region_end = self.region_end;
+        image.reference_line = self.reference_line;
+        image.excluded_pores = self.excluded_pores.clone();
+    }
+
+    // names the changed parameters for the history window
+    fn describe(&self, after: &Self) -> String {
+        let mut changes = Vec::new();
+
+        if self.threshold != after.threshold {
+            changes.push(format!("Threshold {}", after.threshold));
+        }
+        if self.included_min_feature_size != after.included_min_feature_size {
+            changes.push(format!(
+                "Minimal feature size {}",
+                after.included_min_feature_size
+            ));
+        }
+        if self.minimal_pore_size_low != after.minimal_pore_size_low
+            || self.minimal_pore_size_high != after.minimal_pore_size_high
+        {
+            changes.push(format!(
+                "Pore size {} - {}",
+                after.minimal_pore_size_low, after.minimal_pore_size_high
+            ));
+        }
+        if self.region_start != after.region_start || self.region_end != after.region_end {
+            changes.push(match after.region_start {
+                Some(_) => "Region".to_string(),
+                None => "Reset region".to_string(),
+            });
+        }
+        if self.reference_line != after.reference_line {
+            changes.push("Reference line".to_string());
+        }
+        if self.excluded_pores != after.excluded_pores {
+            changes.push(
+                if after.excluded_pores.len() > self.excluded_pores.len() {
+                    "Exclude pore"
+                } else {
+                    "Include pore"
+                }
+                .to_string(),
+            );
+        }
+
+        changes.join(", ")
+    }
+}
+
+// a pixel of the manual mask before and after, `None` means it is not edited
+type MaskChange = ((u32, u32), Option<ManualOverride>, Option<ManualOverride>);
+
+fn mask_changes(before: &ManualOverrides, after: &ManualOverrides) -> Vec<MaskChange> {
+    let changed = before
+        .iter()
+        .filter(|(pixel, value)| after.get(pixel) != Some(value))
+        .map(|(pixel, value)| (*pixel, Some(*value), after.get(pixel).copied()));
+    let added = after
+        .iter()
+        .filter(|(pixel, _)| !before.contains_key(pixel))
+        .map(|(pixel, value)| (*pixel, None, Some(*value)));
+
+    changed.chain(added).collect()
+}
+
+// an image that is not in the list, it is stored like in a project and opened again when it is restored,
+// the decoded images, results and caches of a few large micrographs would take gigabytes
+pub struct StoredImage {
+    id: u64,
+    image: ProjectImage,
+}
+
+fn store_images(images: Vec<ImageData>) -> Vec<StoredImage> {
+    images
+        .iter()
+        .map(|image| StoredImage {
+            id: image.id,
+            image: ProjectImage::from_image(image),
+        })
+        .collect()
+}
+
+// images that can't be opened any more are left out and reported, returns the number of restored images
+fn restore_images(
+    app: &mut PoreDetectionApp,
+    ctx: &egui::Context,
+    index: usize,
+    stored: Vec<StoredImage>,
+) -> usize {
+    let mut images = Vec::new();
+    for StoredImage { id, image } in stored {
+        let path = image.path.clone();
+        match image.into_image_data(ctx, &mut app.import_problems) {
+            // the other commands refer to the image by its id
+            Ok(image) => images.push(ImageData { id, ..image }),
+            Err(err) => {
+                log::warn!("Could not restore {:?}: {}", path, err);
+                app.import_problems
+                    .push(format!("{:?} could not be restored: {}", path, err));
+            }
+        }
+    }
+
+    let count = images.len();
+    app.insert_images(index, images);
+    for i in index..index + count {
+        app.reanalyze_image(i);
+    }
+
+    count
+}
+
+// images are referenced by their id, so the commands stay valid when other images are added or removed
+pub enum Command {
+    EditImage {
+        image_id: u64,
+        before: Box<ImageState>,
+        after: Box<ImageState>,
+    },
+    EditMask {
+        image_id: u64,
+        changes: Vec<MaskChange>,
+    },
+    // the images are stored in the command while they are not in the list,
+    // `count` are in the list at `index` otherwise
+    AddImages {
+        index: usize,
+        count: usize,
+        removed: Vec<StoredImage>,
+    },
+    RemoveImage {
+        index: usize,
+        count: usize,
+        removed: Vec<StoredImage>,
+    },
+}
+
+pub struct HistoryEntry {
+    pub label: String,
+    command: Command,
+}
+
+#[derive(Default)]
+pub struct History {
+    pub undo: Vec<HistoryEntry>,
+    // the most recently undone entry is last
+    pub redo: Vec<HistoryEntry>,
+    // the parameters of the selected image when they were last recorded, see `track_changes`
+    tracked: Option<(u64, ImageState)>,
+    // the manual mask at the start of a brush stroke
+    stroke: Option<(u64, ManualOverrides)>,
+}
+
+impl History {
+    pub fn push(&mut self, label: String, command: Command) {
+        log::info!("History: {}", label);
+
+        self.redo.clear();
+        self.undo.push(HistoryEntry { label, command });
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn begin_stroke(&mut self, image: &ImageData) {
+        if self.stroke.is_none() {
+            self.stroke = Some((image.id, image.manual_overrides.clone()));
+        }
+    }
+
+    pub fn end_stroke(&mut self, image: &ImageData, label: &str) {
+        if let Some((image_id, before)) = self.stroke.take() {
+            if image_id == image.id {
+                self.record_mask_edit(image, &before, label);
+            }
+        }
+    }
+
+    // records a change of the parameters that was not made on the selected image by the user
+    pub fn record_image_edit(&mut self, image: &ImageData, before: ImageState, label: &str) {
+        let after = ImageState::of(image);
+        if self
+            .tracked
+            .as_ref()
+            .is_some_and(|(image_id, _)| *image_id == image.id)
+        {
+            self.tracked = Some((image.id, after.clone()));
+        }
+
+        self.push(
+            format!("{}: {}", image_name(image), label),
+            Command::EditImage {
+                image_id: image.id,
+                before: Box::new(before),
+                after: Box::new(after),
+            },
+        );
+    }
+
+    // records the difference between `before` and the current manual mask of the image
+    pub fn record_mask_edit(&mut self, image: &ImageData, before: &ManualOverrides, label: &str) {
+        let changes = mask_changes(before, &image.manual_overrides);
+        if !changes.is_empty() {
+            self.push(
+                format!("{}: {}", image_name(image), label),
+                Command::EditMask {
+                    image_id: image.id,
+                    changes,
+                },
+            );
+        }
+    }
+}
+
+fn image_name(image: &ImageData) -> String {
+    image
+        .path
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// parameters are changed in many places, so instead of recording every change where it happens
+// the selected image is compared with its last recorded state every frame,
+// a drag is recorded as one change once the mouse is released
+pub fn track_changes(app: &mut PoreDetectionApp, pointer_down: bool) {
+    let selected_id = app
+        .images
+        .selected
+        .and_then(|selected| app.images.images.get(selected))
+        .map(|image| image.id);
+
+    if let Some((image_id, before)) = &app.history.tracked {
+        let image = app.images.images.iter().find(|image| image.id == *image_id);
+
+        if let Some(image) = image {
+            let after = ImageState::of(image);
+            if after != *before && (!pointer_down || selected_id != Some(image.id)) {
+                let label = format!("{}: {}", image_name(image), before.describe(&after));
+                let command = Command::EditImage {
+                    image_id: image.id,
+                    before: Box::new(before.clone()),
+                    after: Box::new(after.clone()),
+                };
+                app.history.tracked = Some((image.id, after));
+                app.history.push(label, command);
+            }
+        }
+    }
+
+    if app.history.tracked.as_ref().map(|(image_id, _)| *image_id) != selected_id {
+        app.history.tracked = selected_id.map(|image_id| {
+            let image = app.images.images.iter().find(|image| image.id == image_id);
+            (image_id, ImageState::of(image.unwrap()))
+        });
+    }
+}
+
+pub fn add_images(app: &mut PoreDetectionApp, images: Vec<ImageData>) {
+    let (index, count) = (app.images.images.len(), images.len());
+    if count == 0 {
+        return;
+    }
+
+    app.insert_images(index, images);
+    app.history.push(
+        format!("Add {} images", count),
+        Command::AddImages {
+            index,
+            count,
+            removed: Vec::new(),
+        },
+    );
+}
+
+pub fn remove_image(app: &mut PoreDetectionApp, index: usize) {
+    let label = format!("Remove {}", image_name(&app.images.images[index]));
+    let removed = store_images(app.remove_images(index, 1));
+    app.history.push(
+        label,
+        Command::RemoveImage {
+            index,
+            count: 1,
+            removed,
+        },
+    );
+}
+
+fn apply(app: &mut PoreDetectionApp, ctx: &egui::Context, command: &mut Command, undo: bool) {
+    let position = |app: &PoreDetectionApp, image_id: u64| {
+        app.images
+            .images
+            .iter()
+            .position(|image| image.id == image_id)
+    };
+
+    match command {
+        Command::EditImage {
+            image_id,
+            before,
+            after,
+        } => {
+            if let Some(index) = position(app, *image_id) {
+                let state = if undo { before } else { after };
+                state.apply(&mut app.images.images[index]);
+                app.images.images[index]
+                    .update_depth_profile(app.depth_profile_settings.band_width);
+                app.reanalyze_image(index);
+            }
+        }
+        Command::EditMask { image_id, changes } => {
+            if let Some(index) = position(app, *image_id) {
+                let overrides = &mut app.images.images[index].manual_overrides;
+                for (pixel, before, after) in changes.iter() {
+                    match if undo { before } else { after } {
+                        Some(value) => overrides.insert(*pixel, *value),
+                        None => overrides.remove(pixel),
+                    };
+                }
+                app.reanalyze_image(index);
+            }
+        }
+        Command::AddImages {
+            index,
+            count,
+            removed,
+        } => {
+            if undo {
+                *removed = store_images(app.remove_images(*index, *count));
+            } else {
+                *count = restore_images(app, ctx, *index, std::mem::take(removed));
+            }
+        }
+        Command::RemoveImage {
+            index,
+            count,
+            removed,
+        } => {
+            if undo {
+                *count = restore_images(app, ctx, *index, std::mem::take(removed));
+            } else {
+                *removed = store_images(app.remove_images(*index, *count));
+            }
+        }
+    }
+
+    // the restored state is not a new change
+    app.history.tracked = None;
+    track_changes(app, false);
+}
+
+pub fn undo(app: &mut PoreDetectionApp, ctx: &egui::Context) {
+    track_changes(app, false);
+
+    if let Some(mut entry) = app.history.undo.pop() {
+        log::info!("Undo: {}", entry.label);
+        apply(app, ctx, &mut entry.command, true);
+        app.history.redo.push(entry);
+    }
+}
+
+pub fn redo(app: &mut PoreDetectionApp, ctx: &egui::Context) {
+    track_changes(app, false);
+
+    if let Some(mut entry) = app.history.redo.pop() {
+        log::info!("Redo: {}", entry.label);
+        apply(app, ctx, &mut entry.command, false);
+        app.history.undo.push(entry);
+    }
+}
+
+// undoes or redoes until `undo_count` entries are applied
+pub fn jump_to(app: &mut PoreDetectionApp, ctx: &egui::Context, undo_count: usize) {
+    while app.history.undo.len() > undo_count {
+        undo(app, ctx);
+    }
+    while app.history.undo.len() < undo_count && !app.history.redo.is_empty() {
+        redo(app, ctx);
+    }
+}
@@ -1,177 +1,700 @@
-use std::{path::PathBuf, thread::JoinHandle};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use egui::TextureHandle;
 use egui_plot::PlotPoint;
-use image::{DynamicImage, Luma};
-use imageproc::definitions::{HasBlack, HasWhite};
+use image::{DynamicImage, GenericImageView, GrayImage, ImageBuffer, Luma, Pixel};
+
+use super::{
+    depth_profile::{compute_depth_profile, DepthBand, ReferenceLine},
+    detection_app::load_texture_into_ctx,
+    excluded_pores::ExcludedPore,
+    ground_truth::{compare_segmentation, SegmentationComparison},
+    histogram::GreyHistogram,
+    job::{next_image_id, AnalysisJob, JobControl},
+    manual_mask::{apply_manual_overrides, ManualOverrides},
+    pipeline::{label_grayscale, Labelling, PipelineCache},
+    pixel_mask::PixelMask,
+    preview::{analyze_preview, preview_factor, PreviewBase},
+    spatial_statistics::{
+        compute_ripley, compute_spatial_statistics, RipleyPoint, SpatialStatistics,
+    },
+    threshold_sweep::ThresholdSweep,
+    tiled::{analyze_tiled, is_large_image, open_large_tiff, LargeImageStrips, StripSource},
+};
+
+pub type LabelImage = ImageBuffer<Luma<u32>, Vec<u32>>;
+
+#[derive(Clone, Debug)]
+pub struct Pore {
+    pub label: u32,
+    pub area: usize,
+    // in image coordinates (y axis pointing down)
+    pub centroid: PlotPoint,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct AnalysisParameters {
+    pub threshold: i16,
+    pub minimal_pore_size_low: f32,
+    pub minimal_pore_size_high: f32,
+    pub included_min_feature_size: f32,
+    pub region_start: Option<PlotPoint>,
+    pub region_end: Option<PlotPoint>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PoreSize {
+    TooSmall,
+    Passed,
+    TooLarge,
+}
+
+impl AnalysisParameters {
+    // the size filter of the analysis, the limits are truncated to whole pixels
+    pub fn pore_size(&self, size: usize) -> PoreSize {
+        if size as i32 <= self.minimal_pore_size_low as i32 {
+            PoreSize::TooSmall
+        } else if size as i32 >= self.minimal_pore_size_high as i32 {
+            PoreSize::TooLarge
+        } else {
+            PoreSize::Passed
+        }
+    }
+
+    // bounds of the region in image coordinates (x start, x end, y start, y end)
+    pub fn image_region(&self, height: u32) -> Option<(u32, u32, u32, u32)> {
+        let (region_start, region_end) = self.region_start.zip(self.region_end)?;
+
+        Some((
+            region_start.x as u32,
+            region_end.x as u32,
+            height - region_start.y as u32,
+            height - region_end.y as u32,
+        ))
+    }
+}
+
+#[derive(Clone)]
+pub struct AnalysisResult {
+    pub green_pixels: PixelMask,
+    pub white_pixels: PixelMask,
+    pub density: f64,
+    pub pores: Vec<Pore>,
+    // pores that passed the filters but were excluded by the user
+    pub excluded_pores: Vec<Pore>,
+    // not kept by the tiled analysis of large images
+    pub labels: Option<Arc<LabelImage>>,
+    // pixel count of every label, including the ones that did not pass the filters
+    pub label_sizes: Arc<Vec<usize>>,
+    pub spatial_statistics: Option<SpatialStatistics>,
+    // only computed if the region has changed since the last one, see `outdated_histogram_region`
+    pub histogram: Option<GreyHistogram>,
+}
 
 #[derive(Clone)]
 pub struct ImageData {
+    pub id: u64,
+    // increased with every analysis, see `AnalysisJob`
+    pub generation: u64,
     pub path: Option<PathBuf>,
-    pub image: Option<DynamicImage>,
+    // shared with the analysis threads, `None` for large TIFF images that are streamed from disk
+    pub image: Option<Arc<DynamicImage>>,
+    // width and height, known without decoding the image
+    pub dimensions: Option<(u32, u32)>,
+    // shown instead of the image if it is too large for a texture
+    pub downsampled: Option<Arc<PreviewBase>>,
     pub image_handle: Option<TextureHandle>,
     pub density: Option<f64>,
-    pub green_pixels: Option<Vec<PlotPoint>>,
+    pub green_pixels: Option<PixelMask>,
+    pub white_pixels: Option<PixelMask>,
     pub region_start: Option<PlotPoint>,
     pub region_end: Option<PlotPoint>,
     pub threshold: i16,
     pub minimal_pore_size_low: f32,
     pub minimal_pore_size_high: f32,
     pub included_min_feature_size: f32,
+    pub reference_line: Option<ReferenceLine>,
+    pub depth_profile: Option<Vec<DepthBand>>,
+    pub pores: Option<Vec<Pore>>,
+    pub spatial_statistics: Option<SpatialStatistics>,
+    pub threshold_sweep: Option<ThresholdSweep>,
+    pub ground_truth_path: Option<PathBuf>,
+    pub ground_truth: Option<GrayImage>,
+    pub segmentation_comparison: Option<SegmentationComparison>,
+    pub manual_overrides: ManualOverrides,
+    pub excluded_pores: Vec<ExcludedPore>,
+    // pores matched by `excluded_pores` in the last analysis
+    pub matched_excluded_pores: Option<Vec<Pore>>,
+    // only kept for the selected image, see `label_image`
+    pub labels: Option<Arc<LabelImage>>,
+    pub label_sizes: Option<Arc<Vec<usize>>>,
+    pub histogram: Option<GreyHistogram>,
+    pub pipeline_cache: PipelineCache,
+    // result on the downsampled image, shown until the full resolution result arrives
+    pub preview: Option<AnalysisResult>,
 }
 
 impl Default for ImageData {
     fn default() -> Self {
         Self {
+            id: next_image_id(),
+            generation: 0,
             path: Default::default(),
             image: Default::default(),
+            dimensions: Default::default(),
+            downsampled: Default::default(),
             image_handle: Default::default(),
             density: Default::default(),
             green_pixels: Default::default(),
+            white_pixels: Default::default(),
             region_start: Default::default(),
             region_end: Default::default(),
             threshold: Default::default(),
             minimal_pore_size_low: 0.0,
             minimal_pore_size_high: i32::MAX as f32,
             included_min_feature_size: 0.0,
+            reference_line: Default::default(),
+            depth_profile: Default::default(),
+            pores: Default::default(),
+            spatial_statistics: Default::default(),
+            threshold_sweep: Default::default(),
+            ground_truth_path: Default::default(),
+            ground_truth: Default::default(),
+            segmentation_comparison: Default::default(),
+            manual_overrides: Default::default(),
+            excluded_pores: Default::default(),
+            matched_excluded_pores: Default::default(),
+            labels: Default::default(),
+            label_sizes: Default::default(),
+            histogram: Default::default(),
+            pipeline_cache: Default::default(),
+            preview: Default::default(),
         }
     }
 }
 
 impl ImageData {
-    pub fn analyze_image(
-        &mut self,
-    ) -> JoinHandle<(
-        std::vec::Vec<egui_plot::PlotPoint>,
-        std::vec::Vec<egui_plot::PlotPoint>,
-        f64,
-    )> {
-        let image = self.image.clone().unwrap();
-        let region_start = self.region_start;
-        let region_end = self.region_end;
-        let threshold = self.threshold;
-        let minimal_pore_size_low = self.minimal_pore_size_low;
-        let minimal_pore_size_high = self.minimal_pore_size_high;
-        let included_min_feature_size = self.included_min_feature_size;
-
-        let handle = std::thread::spawn(move || {
-            let grayscale = image.grayscale().to_luma8();
-            let grayscale_thresh = imageproc::contrast::threshold(
-                &grayscale,
-                threshold.try_into().unwrap(),
-                imageproc::contrast::ThresholdType::Binary,
-            );
+    // large TIFF images are only read strip by strip, large images of other formats are decoded
+    // but shown downsampled like them
+    pub fn open(ctx: &egui::Context, path: PathBuf) -> Result<Self, String> {
+        let (width, height) = image::image_dimensions(&path)
+            .map_err(|err| format!("Could not open {:?}: {}", path, err))?;
+        let factor = preview_factor(width, height);
 
-            // find connected groups of white pixels
-            let labels = imageproc::region_labelling::connected_components(
-                &grayscale_thresh,
-                imageproc::region_labelling::Connectivity::Eight,
-                Luma::white(),
-            );
+        let (image, downsampled) = match open_large_tiff(&path) {
+            Some(mut tiff_strips) => {
+                let downsampled = PreviewBase::from_strips(&mut tiff_strips, factor)
+                    .map_err(|err| format!("Could not read {:?}: {}", path, err))?;
+                (None, Some(Arc::new(downsampled)))
+            }
+            None => {
+                let image = image::open(&path)
+                    .map_err(|err| format!("Could not open {:?}: {}", path, err))?;
+                let downsampled = is_large_image(width, height)
+                    .then(|| Arc::new(PreviewBase::new(&image, factor)));
+                (Some(Arc::new(image)), downsampled)
+            }
+        };
+
+        let image_handle = match (&downsampled, &image) {
+            (Some(downsampled), _) => {
+                load_texture_into_ctx(ctx, &DynamicImage::ImageRgba8(downsampled.image.clone()))
+            }
+            (None, Some(image)) => load_texture_into_ctx(ctx, image),
+            (None, None) => unreachable!("images that are not decoded are always downsampled"),
+        };
 
-            // count the pixels for each group/label
-            let num_labels = labels.iter().max().unwrap_or(&0);
-            let mut labels_to_size = vec![0; *num_labels as usize + 1];
-            labels.enumerate_pixels().for_each(|(_, _, p)| {
-                labels_to_size[p[0] as usize] += 1;
-            });
-
-            // find connected groups of black pixels
-            let black_labels = imageproc::region_labelling::connected_components(
-                &grayscale_thresh,
-                imageproc::region_labelling::Connectivity::Eight,
-                Luma::black(),
+        Ok(Self {
+            image,
+            dimensions: Some((width, height)),
+            downsampled,
+            image_handle: Some(image_handle),
+            path: Some(path),
+            ..Default::default()
+        })
+    }
+
+    pub fn apply_analysis_result(&mut self, result: AnalysisResult, band_width: f64) {
+        let AnalysisResult {
+            green_pixels,
+            white_pixels,
+            density,
+            pores,
+            excluded_pores,
+            labels,
+            label_sizes,
+            spatial_statistics,
+            histogram,
+        } = result;
+
+        self.green_pixels = Some(green_pixels);
+        self.white_pixels = Some(white_pixels);
+        self.density = Some(density);
+        self.pores = Some(pores);
+        self.matched_excluded_pores = Some(excluded_pores);
+        self.labels = labels;
+        self.label_sizes = Some(label_sizes);
+        self.spatial_statistics = spatial_statistics;
+        if histogram.is_some() {
+            self.histogram = histogram;
+        }
+        self.preview = None;
+        self.update_depth_profile(band_width);
+        self.update_segmentation_comparison();
+    }
+
+    pub fn update_depth_profile(&mut self, band_width: f64) {
+        self.depth_profile = match (self.reference_line, &self.green_pixels, &self.white_pixels) {
+            (Some(reference), Some(green_pixels), Some(white_pixels)) => Some(
+                compute_depth_profile(green_pixels, white_pixels, reference, band_width),
+            ),
+            _ => None,
+        };
+    }
+
+    pub fn update_segmentation_comparison(&mut self) {
+        let (Some(ground_truth), Some(green_pixels)) = (&self.ground_truth, &self.green_pixels)
+        else {
+            self.segmentation_comparison = None;
+            return;
+        };
+
+        self.segmentation_comparison = compare_segmentation(
+            ground_truth,
+            green_pixels,
+            self.region_start.zip(self.region_end),
+        );
+        // e.g. the image file was replaced by one of another size
+        if self.segmentation_comparison.is_none() {
+            log::warn!(
+                "Removed the ground truth {:?}, it does not have the size of the image",
+                self.ground_truth_path
             );
+            self.ground_truth = None;
+            self.ground_truth_path = None;
+        }
+    }
 
-            // count the pixels for each group/label
-            let num_labels_black = black_labels.iter().max().unwrap_or(&0);
-            let mut black_labels_to_size = vec![0; *num_labels_black as usize + 1];
-            black_labels.enumerate_pixels().for_each(|(_, _, p)| {
-                black_labels_to_size[p[0] as usize] += 1;
-            });
-
-            // draw a green pixel for each black pixel that is part of a group with a size greater than the users minimal pore size
-            let mut green_pixels = Vec::new();
-            let mut white_pixels = Vec::new();
-            if let (Some(region_start), Some(region_end)) = (region_start, region_end) {
-                labels.enumerate_pixels().for_each(|(x, y, p)| {
-                    let y_start = image.height() - region_start.y as u32;
-                    let y_end = image.height() - region_end.y as u32;
-
-                    if grayscale_thresh.get_pixel(x, y) == &Luma::black()
-                        && labels_to_size[p[0] as usize] > minimal_pore_size_low as i32
-                        && labels_to_size[p[0] as usize] < minimal_pore_size_high as i32
-                        && x >= region_start.x as u32
-                        && x <= region_end.x as u32
-                        && y >= y_start
-                        && y <= y_end
-                    {
-                        green_pixels.push(PlotPoint::new(x, y));
-                    }
-
-                    if grayscale_thresh.get_pixel(x, y) == &Luma::white()
-                        && x >= region_start.x as u32
-                        && x <= region_end.x as u32
-                        && y >= y_start
-                        && y <= y_end
-                    {
-                        white_pixels.push(PlotPoint::new(x, y));
-                    }
-                });
+    // smallest and largest area of the pores found in the last analysis
+    pub fn pore_size_range(&self) -> Option<(usize, usize)> {
+        let pores = self.pores.as_ref()?;
+        let min_area = pores.iter().map(|pore| pore.area).min()?;
+        let max_area = pores.iter().map(|pore| pore.area).max()?;
 
-                if included_min_feature_size > 0.0 {
-                    black_labels.enumerate_pixels().for_each(|(x, y, p)| {
-                        let y_start = image.height() - region_start.y as u32;
-                        let y_end = image.height() - region_end.y as u32;
-
-                        if black_labels_to_size[p[0] as usize] < included_min_feature_size as i32
-                            && x >= region_start.x as u32
-                            && x <= region_end.x as u32
-                            && y >= y_start
-                            && y <= y_end
-                        {
-                            green_pixels.push(PlotPoint::new(x, y));
-
-                            if white_pixels.contains(&PlotPoint::new(x, y)) {
-                                white_pixels.retain(|p| p != &PlotPoint::new(x, y));
-                            }
-                        }
-                    });
+        Some((min_area, max_area))
+    }
+
+    // ripley's K of the pores of the last analysis, computed again unless `fill_ripley` kept it
+    pub fn ripley(&self) -> Option<Vec<RipleyPoint>> {
+        let statistics = self.spatial_statistics.as_ref()?;
+        if let Some(ripley) = &statistics.ripley {
+            return Some(ripley.clone());
+        }
+
+        let centroids = self
+            .pores
+            .as_ref()?
+            .iter()
+            .map(|pore| pore.centroid)
+            .collect::<Vec<_>>();
+        Some(compute_ripley(
+            &centroids,
+            statistics.area,
+            statistics.max_ripley_radius,
+        ))
+    }
+
+    // keeps ripley's K until the next analysis, so it is not computed for every frame it is shown
+    pub fn fill_ripley(&mut self) {
+        if self
+            .spatial_statistics
+            .as_ref()
+            .is_some_and(|statistics| statistics.ripley.is_none())
+        {
+            let ripley = self.ripley();
+            if let Some(statistics) = &mut self.spatial_statistics {
+                statistics.ripley = ripley;
+            }
+        }
+    }
+
+    // large images are never labelled or converted to grayscale as a whole
+    pub fn is_large(&self) -> bool {
+        self.dimensions
+            .is_some_and(|(width, height)| is_large_image(width, height))
+    }
+
+    // the downsampled image a preview is drawn on
+    pub fn preview_base(&self) -> Option<Arc<PreviewBase>> {
+        self.downsampled
+            .clone()
+            .or_else(|| self.pipeline_cache.preview())
+    }
+
+    // `None` for images that are streamed from disk
+    pub fn grey_value(&self, x: u32, y: u32) -> Option<u8> {
+        Some(self.image.as_ref()?.get_pixel(x, y).to_luma()[0])
+    }
+
+    // the histogram is computed with the analysis, `None` until it covers the current region
+    pub fn histogram(&self) -> Option<&GreyHistogram> {
+        self.histogram
+            .as_ref()
+            .filter(|_| self.outdated_histogram_region().is_none())
+    }
+
+    // the region the next analysis has to compute a histogram for, `None` if the last one still fits
+    pub fn outdated_histogram_region(&self) -> Option<Option<(u32, u32, u32, u32)>> {
+        let (_, height) = self.dimensions?;
+        let region = self.analysis_parameters().image_region(height);
+
+        (self.histogram.as_ref().map(|histogram| histogram.region) != Some(region))
+            .then_some(region)
+    }
+
+    pub fn analysis_parameters(&self) -> AnalysisParameters {
+        AnalysisParameters {
+            threshold: self.threshold,
+            minimal_pore_size_low: self.minimal_pore_size_low,
+            minimal_pore_size_high: self.minimal_pore_size_high,
+            included_min_feature_size: self.included_min_feature_size,
+            region_start: self.region_start,
+            region_end: self.region_end,
+        }
+    }
+
+    // starts a new generation, results of older analyses of this image are ignored from now on
+    pub fn analyze_image(&mut self) -> AnalysisJob {
+        let cache = self.pipeline_cache.clone();
+        let image = self.image.clone();
+        let path = self.path.clone();
+        let is_large = self.is_large();
+        let parameters = self.analysis_parameters();
+        let histogram_region = self.outdated_histogram_region();
+        let manual_overrides = self.manual_overrides.clone();
+        let excluded_pores = self.excluded_pores.clone();
+        let control = Arc::new(JobControl::default());
+
+        self.generation += 1;
+
+        AnalysisJob {
+            image_id: self.id,
+            generation: self.generation,
+            control: control.clone(),
+            preview: false,
+            handle: std::thread::spawn(move || {
+                if is_large {
+                    return analyze_large_image(
+                        image.as_deref(),
+                        path.as_deref(),
+                        &parameters,
+                        histogram_region,
+                        &manual_overrides,
+                        &excluded_pores,
+                        &control,
+                    )
+                    .inspect_err(|err| log::warn!("Tiled analysis failed: {}", err))
+                    .ok()?;
                 }
-            } else {
-                labels.enumerate_pixels().for_each(|(x, y, p)| {
-                    if grayscale_thresh.get_pixel(x, y) == &Luma::black()
-                        && labels_to_size[p[0] as usize] > minimal_pore_size_low as i32
-                        && labels_to_size[p[0] as usize] < minimal_pore_size_high as i32
-                    {
-                        green_pixels.push(PlotPoint::new(x, y));
-                    }
-
-                    if grayscale_thresh.get_pixel(x, y) == &Luma::white() {
-                        white_pixels.push(PlotPoint::new(x, y));
-                    }
+
+                let grayscale =
+                    cache.grayscale_or_insert_with(|| image.unwrap().grayscale().to_luma8());
+                let labelling =
+                    cache.labelling_or_insert(&grayscale, parameters.threshold, &control)?;
+
+                let mut result = filter_labels(
+                    &labelling,
+                    &parameters,
+                    &manual_overrides,
+                    &excluded_pores,
+                    &control,
+                )?;
+                add_spatial_statistics(
+                    &mut result,
+                    &parameters,
+                    grayscale.width(),
+                    grayscale.height(),
+                );
+                result.histogram =
+                    histogram_region.map(|region| GreyHistogram::new(&grayscale, region));
+
+                Some(result)
+            }),
+        }
+    }
+
+    // `None` if the image is small enough to be analyzed at full resolution while dragging
+    pub fn preview_image(&mut self) -> Option<AnalysisJob> {
+        let (width, height) = self.dimensions?;
+        let factor = preview_factor(width, height);
+        if factor == 1 {
+            return None;
+        }
+
+        let cache = self.pipeline_cache.clone();
+        let base = self.preview_base();
+        let image = match base {
+            Some(_) => None,
+            None => Some(self.image.clone()?),
+        };
+        let parameters = self.analysis_parameters();
+        let manual_overrides = self.manual_overrides.clone();
+        let excluded_pores = self.excluded_pores.clone();
+        let control = Arc::new(JobControl::default());
+
+        self.generation += 1;
+
+        Some(AnalysisJob {
+            image_id: self.id,
+            generation: self.generation,
+            control: control.clone(),
+            preview: true,
+            handle: std::thread::spawn(move || {
+                let base = base.unwrap_or_else(|| {
+                    cache.preview_or_insert_with(|| PreviewBase::new(&image.unwrap(), factor))
                 });
 
-                if included_min_feature_size > 0.0 {
-                    black_labels.enumerate_pixels().for_each(|(x, y, p)| {
-                        if black_labels_to_size[p[0] as usize] < included_min_feature_size as i32 {
-                            green_pixels.push(PlotPoint::new(x, y));
+                analyze_preview(
+                    &base,
+                    &parameters,
+                    &manual_overrides,
+                    &excluded_pores,
+                    &control,
+                )
+            }),
+        })
+    }
 
-                            if white_pixels.contains(&PlotPoint::new(x, y)) {
-                                white_pixels.retain(|p| p != &PlotPoint::new(x, y));
-                            }
-                        }
-                    });
-                }
+    // the labels of the last analysis, images that are not selected are labelled again without filling their cache
+    pub fn label_image(&self) -> Option<Arc<LabelImage>> {
+        if let Some(labels) = &self.labels {
+            return Some(labels.clone());
+        }
+        if self.is_large() {
+            return None;
+        }
+        if let Some(labelling) = self.pipeline_cache.labelling(self.threshold) {
+            return Some(labelling.labels.clone());
+        }
+
+        let grayscale = match self.pipeline_cache.grayscale() {
+            Some(grayscale) => grayscale,
+            None => Arc::new(self.image.as_ref()?.grayscale().to_luma8()),
+        };
+        let labelling = label_grayscale(&grayscale, self.threshold, &JobControl::default())?;
+
+        Some(labelling.labels)
+    }
+
+    pub fn grayscale(&self) -> Option<Arc<GrayImage>> {
+        let image = self.image.as_ref()?;
+        Some(
+            self.pipeline_cache
+                .grayscale_or_insert_with(|| image.grayscale().to_luma8()),
+        )
+    }
+}
+
+// large images are analyzed strip by strip, TIFF files are streamed from disk without being decoded
+// and other images are converted to grayscale one strip at a time, `Ok(None)` if the analysis was cancelled,
+// the histogram of `histogram_region` needs another pass over the strips
+pub fn analyze_large_image(
+    image: Option<&DynamicImage>,
+    path: Option<&Path>,
+    parameters: &AnalysisParameters,
+    histogram_region: Option<Option<(u32, u32, u32, u32)>>,
+    manual_overrides: &ManualOverrides,
+    excluded_pores: &[ExcludedPore],
+    control: &JobControl,
+) -> Result<Option<AnalysisResult>, String> {
+    let mut strips = LargeImageStrips::open(image, path)?;
+    let (width, height) = strips.dimensions();
+    let result = analyze_tiled(
+        &mut strips,
+        parameters,
+        manual_overrides,
+        excluded_pores,
+        control,
+    )?;
+    let Some(mut result) = result else {
+        return Ok(None);
+    };
+    add_spatial_statistics(&mut result, parameters, width, height);
+
+    if let Some(region) = histogram_region {
+        let mut strips = LargeImageStrips::open(image, path)?;
+        result.histogram = Some(GreyHistogram::from_strips(&mut strips, region)?);
+    }
+
+    Ok(Some(result))
+}
+
+// spatial statistics are computed on the analyzed area only
+pub fn add_spatial_statistics(
+    result: &mut AnalysisResult,
+    parameters: &AnalysisParameters,
+    width: u32,
+    height: u32,
+) {
+    let (area_width, area_height) = if let (Some(region_start), Some(region_end)) =
+        (parameters.region_start, parameters.region_end)
+    {
+        (
+            (region_end.x - region_start.x).abs(),
+            (region_start.y - region_end.y).abs(),
+        )
+    } else {
+        (width as f64, height as f64)
+    };
+    let centroids = result
+        .pores
+        .iter()
+        .map(|pore| pore.centroid)
+        .collect::<Vec<_>>();
+    result.spatial_statistics = compute_spatial_statistics(
+        &centroids,
+        area_width * area_height,
+        area_width.min(area_height) / 4.0,
+    );
+}
+
+pub fn analyze_grayscale(
+    grayscale: &GrayImage,
+    parameters: &AnalysisParameters,
+    manual_overrides: &ManualOverrides,
+    excluded_pores: &[ExcludedPore],
+    control: &JobControl,
+) -> Option<AnalysisResult> {
+    let labelling = label_grayscale(grayscale, parameters.threshold, control)?;
+    filter_labels(
+        &labelling,
+        parameters,
+        manual_overrides,
+        excluded_pores,
+        control,
+    )
+}
+
+// everything after the labelling, this is all that runs when only the filters, region or edits change
+pub fn filter_labels(
+    labelling: &Labelling,
+    parameters: &AnalysisParameters,
+    manual_overrides: &ManualOverrides,
+    excluded_pores: &[ExcludedPore],
+    control: &JobControl,
+) -> Option<AnalysisResult> {
+    let included_min_feature_size = parameters.included_min_feature_size;
+    let Labelling {
+        labels,
+        label_sizes,
+        black_labels,
+        black_label_sizes,
+        ..
+    } = labelling;
+
+    let (width, height) = labels.dimensions();
+    let region = parameters.image_region(height);
+    let is_in_region = |x: u32, y: u32| match region {
+        Some((x_start, x_end, y_start, y_end)) => {
+            x >= x_start && x <= x_end && y >= y_start && y <= y_end
+        }
+        None => true,
+    };
+
+    // mark each black pixel that is part of a group with a size greater than the users minimal pore size
+    let mut green_pixels = PixelMask::new(width, height);
+    let mut white_pixels = PixelMask::new(width, height);
+    // sum of x, sum of y and number of green pixels for each label
+    let mut label_sums = vec![(0.0, 0.0, 0); label_sizes.len()];
+    labels.enumerate_pixels().for_each(|(x, y, p)| {
+        if !is_in_region(x, y) {
+            return;
+        }
+
+        // only black pixels have a label
+        if p[0] != 0 {
+            if parameters.pore_size(label_sizes[p[0] as usize]) == PoreSize::Passed {
+                green_pixels.set(x, y, true);
+
+                let sums = &mut label_sums[p[0] as usize];
+                sums.0 += x as f64;
+                sums.1 += y as f64;
+                sums.2 += 1;
             }
-            log::info!("pushed black pixels: {:?}", green_pixels.len());
+        } else {
+            white_pixels.set(x, y, true);
+        }
+    });
 
-            // calculate the density for the whole image
-            let density = (1.0 - (green_pixels.len() as f64 / white_pixels.len() as f64)) * 100.0;
+    if control.is_cancelled() {
+        return None;
+    }
+
+    // small groups of white pixels (e.g. corrosion or scratches inside a pore) count as pore
+    if included_min_feature_size > 0.0 {
+        black_labels.enumerate_pixels().for_each(|(x, y, p)| {
+            if (black_label_sizes[p[0] as usize] as i32) < included_min_feature_size as i32
+                && is_in_region(x, y)
+            {
+                green_pixels.set(x, y, true);
+                white_pixels.set(x, y, false);
+            }
+        });
+    }
+    log::info!("marked black pixels: {:?}", green_pixels.count());
 
-            (green_pixels, white_pixels, density)
+    let (excluded, pores): (Vec<_>, Vec<_>) = label_sums
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, _, count))| *count > 0)
+        .map(|(label, (sum_x, sum_y, count))| Pore {
+            label: label as u32,
+            area: *count,
+            centroid: PlotPoint::new(sum_x / *count as f64, sum_y / *count as f64),
+        })
+        .partition(|pore| {
+            excluded_pores
+                .iter()
+                .any(|excluded_pore| excluded_pore.matches(pore))
         });
 
-        handle
+    // excluded pores are treated like pores outside of the size range
+    if !excluded.is_empty() {
+        let mut is_excluded = vec![false; label_sizes.len()];
+        excluded
+            .iter()
+            .for_each(|pore| is_excluded[pore.label as usize] = true);
+
+        labels.enumerate_pixels().for_each(|(x, y, p)| {
+            if is_excluded[p[0] as usize] {
+                green_pixels.set(x, y, false);
+            }
+        });
     }
+
+    // manual edits always win over the automatic segmentation
+    apply_manual_overrides(
+        &mut green_pixels,
+        &mut white_pixels,
+        manual_overrides,
+        region,
+    );
+
+    // calculate the density for the whole image
+    let density = (1.0 - (green_pixels.count() as f64 / white_pixels.count() as f64)) * 100.0;
+
+    Some(AnalysisResult {
+        green_pixels,
+        white_pixels,
+        density,
+        pores,
+        excluded_pores: excluded,
+        labels: Some(labels.clone()),
+        label_sizes: label_sizes.clone(),
+        spatial_statistics: None,
+        histogram: None,
+    })
 }
@@ -1,10 +1,105 @@
+use std::path::Path;
+
 use egui_plot::PlotPoint;
 use rfd::FileDialog;
-use rust_xlsxwriter::{workbook::Workbook, Table, TableColumn};
+use rust_xlsxwriter::{
+    workbook::Workbook, worksheet::Worksheet, Format, Table, TableColumn, TableFunction, XlsxError,
+};
 
 use crate::view::export_window::ExportDecimalFormat;
 
-use super::image_data::ImageData;
+use super::{
+    csv::write_csv_line,
+    excel_export::{write_parameters_sheet, write_pore_sheets, write_summary_sheet, RESULTS_SHEET},
+    excluded_pores::format_excluded_pores,
+    image_data::ImageData,
+    manual_mask::override_runs,
+};
+
+pub const RESULT_HEADERS: [&str; 16] = [
+    "Filename",
+    "Density",
+    "Threshold",
+    "Min Feature Size",
+    "Lower Pore Size",
+    "Upper Pore Size",
+    "Selected Region",
+    "File Path",
+    "Pore Count",
+    "Mean NN Distance",
+    "Mean Spacing",
+    "Clark-Evans Index",
+    "Density Uncertainty",
+    "Uncertainty Grey Levels (±)",
+    "Manual Pixels",
+    "Excluded Pores",
+];
+
+// of the density and the other decimals in the Excel and CSV export
+const DECIMAL_PLACES: usize = 5;
+
+// a cell of the results table, numbers are written as numbers to Excel and formatted for CSV
+pub enum ResultCell {
+    Text(String),
+    // parameters are written without trailing zeros
+    Parameter(f32),
+    Decimal(f64),
+    Whole(i64),
+    Empty,
+}
+
+impl ResultCell {
+    fn to_text(&self, export_num_type: &ExportDecimalFormat) -> String {
+        match self {
+            ResultCell::Text(text) => text.clone(),
+            ResultCell::Parameter(value) => format_parameter(*value, export_num_type),
+            ResultCell::Decimal(value) => format_decimal(*value, export_num_type),
+            ResultCell::Whole(value) => value.to_string(),
+            ResultCell::Empty => String::new(),
+        }
+    }
+
+    pub fn write(
+        &self,
+        worksheet: &mut Worksheet,
+        row: u32,
+        column: u16,
+        formats: &NumberFormats,
+    ) -> Result<(), XlsxError> {
+        match self {
+            ResultCell::Text(text) => worksheet.write_string(row, column, text)?,
+            // the shortest representation of the f32, so 0.1 is not written as 0.10000000149011612
+            ResultCell::Parameter(value) => {
+                let value = value.to_string().parse().unwrap_or(*value as f64);
+                worksheet.write_number(row, column, value)?
+            }
+            ResultCell::Decimal(value) => {
+                worksheet.write_number_with_format(row, column, *value, &formats.decimal)?
+            }
+            ResultCell::Whole(value) => {
+                worksheet.write_number_with_format(row, column, *value as f64, &formats.whole)?
+            }
+            ResultCell::Empty => worksheet,
+        };
+
+        Ok(())
+    }
+}
+
+pub struct NumberFormats {
+    pub decimal: Format,
+    pub whole: Format,
+}
+
+// the decimal separator of the Excel export is chosen by Excel
+impl Default for NumberFormats {
+    fn default() -> Self {
+        Self {
+            decimal: Format::new().set_num_format(format!("0.{}", "0".repeat(DECIMAL_PLACES))),
+            whole: Format::new().set_num_format("0"),
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct Images {
@@ -14,73 +109,301 @@ pub struct Images {
 }
 
 impl Images {
-    pub fn export(&self, export_num_type: ExportDecimalFormat) {
+    // one row per image with the columns of `RESULT_HEADERS`, shared by the Excel and CSV export
+    pub fn result_rows(&self, uncertainty_levels: i16) -> Vec<Vec<ResultCell>> {
+        self.images
+            .iter()
+            .map(|image| {
+                let filename = image
+                    .path
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap();
+
+                let threshold = image.threshold;
+                let decimal =
+                    |value: Option<f64>| value.map_or(ResultCell::Empty, ResultCell::Decimal);
+
+                let pore_count = image.pores.as_ref().map_or(ResultCell::Empty, |pores| {
+                    ResultCell::Whole(pores.len() as i64)
+                });
+                let statistics = image.spatial_statistics.as_ref();
+
+                // only report the uncertainty if the sweep was done with the current parameters
+                let (density_uncertainty, grey_levels) = match &image.threshold_sweep {
+                    Some(sweep) if sweep.matches(&image.analysis_parameters()) => (
+                        decimal(sweep.uncertainty(image.threshold, uncertainty_levels)),
+                        ResultCell::Whole(uncertainty_levels as i64),
+                    ),
+                    _ => (ResultCell::Empty, ResultCell::Empty),
+                };
+
+                vec![
+                    ResultCell::Text(filename.to_string()),
+                    decimal(image.density),
+                    ResultCell::Whole(threshold as i64),
+                    ResultCell::Parameter(image.included_min_feature_size),
+                    ResultCell::Parameter(image.minimal_pore_size_low),
+                    ResultCell::Parameter(image.minimal_pore_size_high),
+                    ResultCell::Text(format_region(image)),
+                    ResultCell::Text(image.path.as_ref().unwrap().to_str().unwrap().to_string()),
+                    pore_count,
+                    decimal(
+                        statistics.map(|statistics| statistics.mean_nearest_neighbour_distance),
+                    ),
+                    decimal(statistics.map(|statistics| statistics.mean_spacing)),
+                    decimal(statistics.map(|statistics| statistics.clark_evans_index)),
+                    density_uncertainty,
+                    grey_levels,
+                    ResultCell::Whole(image.manual_overrides.len() as i64),
+                    ResultCell::Text(format_excluded_pores(&image.excluded_pores)),
+                ]
+            })
+            .collect()
+    }
+
+    // numbers are stored as numbers, so the decimal format is chosen by Excel
+    pub fn export(&self, project_path: Option<&Path>, uncertainty_levels: i16) {
         let mut workbook = Workbook::new();
-        let worksheet = workbook.add_worksheet();
+        let formats = NumberFormats::default();
+        let worksheet = workbook.add_worksheet().set_name(RESULTS_SHEET).unwrap();
 
-        for (i, image) in self.images.iter().enumerate() {
-            let filename = image
-                .path
-                .as_ref()
-                .unwrap()
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap();
+        for (i, row) in self.result_rows(uncertainty_levels).iter().enumerate() {
+            for (column, cell) in row.iter().enumerate() {
+                cell.write(worksheet, i as u32 + 1, column as u16, &formats)
+                    .unwrap();
+            }
+        }
+
+        // the total row is skipped by the import because of its label
+        let columns = RESULT_HEADERS
+            .iter()
+            .map(|header| {
+                let column = TableColumn::new().set_header(*header);
+                match *header {
+                    "Filename" => column.set_total_label("Total"),
+                    "Density" => column
+                        .set_total_function(TableFunction::Average)
+                        .set_format(formats.decimal.clone()),
+                    _ => column,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let table = Table::new()
+            .set_columns(&columns)
+            .set_total_row(true)
+            .set_banded_rows(true);
+        worksheet
+            .add_table(
+                0,
+                0,
+                // the header and the total row are part of the table
+                (self.images.len() + 1).try_into().unwrap(),
+                (columns.len() - 1).try_into().unwrap(),
+                &table,
+            )
+            .unwrap();
+        worksheet.set_freeze_panes(1, 1).unwrap();
+        worksheet.autofit();
+
+        write_summary_sheet(&mut workbook, &self.images, &formats).unwrap();
+        write_parameters_sheet(&mut workbook, &self.images, project_path, &formats).unwrap();
 
-            let density = match export_num_type {
-                ExportDecimalFormat::Dot => format!("{:.5}", image.density.unwrap_or(0.0)),
-                ExportDecimalFormat::Comma => {
-                    format!("{:.5}", image.density.unwrap_or(0.0)).replace(".", ",")
+        // ripley's K and L function of every image on a separate sheet
+        let ripley_worksheet = workbook.add_worksheet().set_name("Ripley").unwrap();
+        ripley_worksheet
+            .write_row(0, 0, ["Filename", "Radius [px]", "K(r)", "L(r)"])
+            .unwrap();
+
+        let mut ripley_row = 1;
+        for image in &self.images {
+            let (Some(path), Some(ripley)) = (&image.path, image.ripley()) else {
+                continue;
+            };
+            let filename = path.file_name().unwrap().to_str().unwrap();
+
+            for point in &ripley {
+                ripley_worksheet
+                    .write_string(ripley_row, 0, filename)
+                    .unwrap();
+                for (column, value) in [point.radius, point.k, point.l].into_iter().enumerate() {
+                    ResultCell::Decimal(value)
+                        .write(ripley_worksheet, ripley_row, column as u16 + 1, &formats)
+                        .unwrap();
                 }
+                ripley_row += 1;
+            }
+        }
+        ripley_worksheet.autofit();
+
+        // manual edits are stored so the project can be loaded again
+        let overrides_worksheet = workbook
+            .add_worksheet()
+            .set_name("Manual Overrides")
+            .unwrap();
+        overrides_worksheet
+            .write_row(0, 0, ["File Path", "X", "Y Start", "Y End", "Override"])
+            .unwrap();
+
+        let mut overrides_row = 1;
+        for image in &self.images {
+            let Some(path) = &image.path else {
+                continue;
             };
 
-            let threshold = image.threshold;
-            let start = image.region_start.unwrap_or(PlotPoint::new(0.0, 0.0));
-            let end = image.region_end.unwrap_or(PlotPoint::new(0.0, 0.0));
-
-            let row = [
-                filename,
-                &format!("{:.5}", density),
-                &threshold.to_string(),
-                &image.included_min_feature_size.to_string(),
-                &image.minimal_pore_size_low.to_string(),
-                &image.minimal_pore_size_high.to_string(),
-                &format!(
-                    "({:.2}, {:.2}) - ({:.2}, {:.2})",
-                    start.x, start.y, end.x, end.y
-                ),
-                image.path.as_ref().unwrap().to_str().unwrap(),
-            ];
+            for (x, y_start, y_end, value) in override_runs(&image.manual_overrides) {
+                overrides_worksheet
+                    .write_string(overrides_row, 0, path.to_str().unwrap())
+                    .unwrap();
+                overrides_worksheet
+                    .write_number(overrides_row, 1, x)
+                    .unwrap();
+                overrides_worksheet
+                    .write_number(overrides_row, 2, y_start)
+                    .unwrap();
+                overrides_worksheet
+                    .write_number(overrides_row, 3, y_end)
+                    .unwrap();
+                overrides_worksheet
+                    .write_string(overrides_row, 4, value.to_string())
+                    .unwrap();
+                overrides_row += 1;
+            }
+        }
+
+        // one sheet per image after all other sheets, there can be many of them
+        write_pore_sheets(&mut workbook, &self.images, &formats).unwrap();
+
+        let path = FileDialog::new().add_filter("Excel", &["xlsx"]).save_file();
+        if let Some(path) = path {
+            workbook.save(path).unwrap();
+        }
+    }
+
+    // the same table as the Excel export, the comma format uses `;` to separate the fields
+    pub fn export_csv(
+        &self,
+        export_num_type: ExportDecimalFormat,
+        tab_separated: bool,
+        uncertainty_levels: i16,
+    ) {
+        let separator = match (tab_separated, &export_num_type) {
+            (true, _) => '\t',
+            (false, ExportDecimalFormat::Dot) => ',',
+            (false, ExportDecimalFormat::Comma) => ';',
+        };
+
+        let mut text = write_csv_line(&RESULT_HEADERS, separator);
+        for row in self.result_rows(uncertainty_levels) {
+            let row = row
+                .iter()
+                .map(|cell| cell.to_text(&export_num_type))
+                .collect::<Vec<_>>();
+            text += &write_csv_line(&row, separator);
+        }
+
+        let (filter, extension) = if tab_separated {
+            ("TSV", "tsv")
+        } else {
+            ("CSV", "csv")
+        };
+        let path = FileDialog::new()
+            .add_filter(filter, &[extension])
+            .set_file_name(format!("results.{}", extension))
+            .save_file();
+        if let Some(path) = path {
+            std::fs::write(path, text).unwrap();
+        }
+    }
+
+    pub fn export_depth_profile(&self, index: usize) {
+        let image = &self.images[index];
+        let Some(depth_profile) = &image.depth_profile else {
+            return;
+        };
+
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
 
+        for (i, band) in depth_profile.iter().enumerate() {
+            let row: u32 = (i + 1).try_into().unwrap();
+            worksheet.write_number(row, 0, band.start).unwrap();
+            worksheet.write_number(row, 1, band.end).unwrap();
             worksheet
-                .write_row((i + 1).try_into().unwrap(), 0, row)
+                .write_number(row, 2, band.pore_pixels as f64)
                 .unwrap();
+            worksheet
+                .write_number(row, 3, band.material_pixels as f64)
+                .unwrap();
+            match band.porosity() {
+                Some(porosity) => worksheet.write_number(row, 4, porosity).unwrap(),
+                None => worksheet.write_string(row, 4, "no data").unwrap(),
+            };
         }
 
         let columns = vec![
-            TableColumn::new().set_header("Filename"),
-            TableColumn::new().set_header("Density"),
-            TableColumn::new().set_header("Threshold"),
-            TableColumn::new().set_header("Min Feature Size"),
-            TableColumn::new().set_header("Lower Pore Size"),
-            TableColumn::new().set_header("Upper Pore Size"),
-            TableColumn::new().set_header("Selected Region"),
-            TableColumn::new().set_header("File Path"),
+            TableColumn::new().set_header("Band Start [px]"),
+            TableColumn::new().set_header("Band End [px]"),
+            TableColumn::new().set_header("Pore Pixels"),
+            TableColumn::new().set_header("Material Pixels"),
+            TableColumn::new().set_header("Porosity [%]"),
         ];
 
-        let table = Table::new()
-            .set_columns(&columns)
-            .set_total_row(true)
-            .set_banded_rows(true);
+        let table = Table::new().set_columns(&columns).set_banded_rows(true);
         worksheet
-            .add_table(0, 0, self.images.len().try_into().unwrap(), 6, &table)
+            .add_table(0, 0, depth_profile.len().try_into().unwrap(), 4, &table)
             .unwrap();
+
+        if let Some(reference_line) = image.reference_line {
+            worksheet
+                .write_string(0, 6, format!("Reference: {}", reference_line))
+                .unwrap();
+        }
         worksheet.autofit();
 
-        let path = FileDialog::new().add_filter("Excel", &["xlsx"]).save_file();
+        let file_name = image
+            .path
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .map(|stem| format!("{}_depth_profile.xlsx", stem.to_string_lossy()))
+            .unwrap_or("depth_profile.xlsx".to_string());
+
+        let path = FileDialog::new()
+            .add_filter("Excel", &["xlsx"])
+            .set_file_name(file_name)
+            .save_file();
         if let Some(path) = path {
             workbook.save(path).unwrap();
         }
     }
 }
+
+// "(x, y) - (x, y)" in plot coordinates, read again by the Excel import
+pub fn format_region(image: &ImageData) -> String {
+    let start = image.region_start.unwrap_or(PlotPoint::new(0.0, 0.0));
+    let end = image.region_end.unwrap_or(PlotPoint::new(0.0, 0.0));
+
+    format!(
+        "({:.2}, {:.2}) - ({:.2}, {:.2})",
+        start.x, start.y, end.x, end.y
+    )
+}
+
+// parameters are written without trailing zeros
+fn format_parameter(value: f32, export_num_type: &ExportDecimalFormat) -> String {
+    match export_num_type {
+        ExportDecimalFormat::Dot => value.to_string(),
+        ExportDecimalFormat::Comma => value.to_string().replace(".", ","),
+    }
+}
+
+fn format_decimal(value: f64, export_num_type: &ExportDecimalFormat) -> String {
+    match export_num_type {
+        ExportDecimalFormat::Dot => format!("{:.*}", DECIMAL_PLACES, value),
+        ExportDecimalFormat::Comma => format!("{:.*}", DECIMAL_PLACES, value).replace(".", ","),
+    }
+}
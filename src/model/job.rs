@@ -0,0 +1,53 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use super::image_data::AnalysisResult;
+
+static NEXT_IMAGE_ID: AtomicU64 = AtomicU64::new(1);
+
+// stays the same for an image even if images before it are removed
+pub fn next_image_id() -> u64 {
+    NEXT_IMAGE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// shared between a job and the ui, the analysis checks it between its stages
+#[derive(Default)]
+pub struct JobControl {
+    pub cancel: AtomicBool,
+    pub paused: AtomicBool,
+}
+
+impl JobControl {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+// an analysis of one image, the result is only valid while the image still has the same generation
+pub struct AnalysisJob {
+    pub image_id: u64,
+    pub generation: u64,
+    pub control: Arc<JobControl>,
+    // a preview runs on the downsampled image while a slider is dragged
+    pub preview: bool,
+    pub handle: JoinHandle<Option<AnalysisResult>>,
+}
+
+impl AnalysisJob {
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
@@ -0,0 +1,355 @@
+use std::path::Path;
+
+use jiff::Timestamp;
+use rfd::FileDialog;
+use serde::Serialize;
+
+use super::{
+    detection_app::PoreDetectionApp,
+    image_data::{ImageData, Pore},
+    project::PROJECT_VERSION,
+    relink::file_fingerprint,
+};
+
+// has to be increased together with the `$id` of the schema whenever the structure of the export changes
+pub const RESULTS_SCHEMA_VERSION: u32 = 1;
+
+pub const RESULTS_SCHEMA: &str = include_str!("../../schema/results.schema.json");
+
+pub const RESULTS_SCHEMA_FILE: &str = "results.schema.json";
+
+// the current time for the provenance of exports in UTC as ISO 8601, e.g. `2025-03-01T12:30:00Z`
+pub fn export_timestamp() -> String {
+    Timestamp::now().strftime("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+// the fields of the structs below are written in this order and have to match the schema,
+// options are written as null and JSON has no NaN or infinity, so those become null as well
+#[derive(Serialize)]
+pub struct ResultsJson {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    schema_version: u32,
+    project: ProjectJson,
+    provenance: ProvenanceJson,
+    calibration: CalibrationJson,
+    images: Vec<ImageJson>,
+}
+
+#[derive(Serialize)]
+struct ProjectJson {
+    path: Option<String>,
+    project_version: u32,
+    image_count: usize,
+}
+
+#[derive(Serialize)]
+struct ProvenanceJson {
+    program: &'static str,
+    version: &'static str,
+    exported_at: String,
+}
+
+// there is no calibration yet, all lengths are in pixels and all areas in square pixels
+#[derive(Serialize)]
+struct CalibrationJson {
+    unit: &'static str,
+    pixel_size: f64,
+}
+
+// bounds in image coordinates (y axis pointing down), inclusive
+#[derive(Serialize)]
+struct RegionJson {
+    x_start: u32,
+    x_end: u32,
+    y_start: u32,
+    y_end: u32,
+}
+
+impl RegionJson {
+    fn new(region: Option<(u32, u32, u32, u32)>) -> Option<Self> {
+        region.map(|(x_start, x_end, y_start, y_end)| Self {
+            x_start,
+            x_end,
+            y_start,
+            y_end,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ParametersJson {
+    threshold: i16,
+    minimal_pore_size_low: f32,
+    minimal_pore_size_high: f32,
+    included_min_feature_size: f32,
+    region: Option<RegionJson>,
+    manual_pixels: usize,
+    excluded_pores: usize,
+}
+
+#[derive(Serialize)]
+struct UncertaintyJson {
+    density: f64,
+    grey_levels: i16,
+}
+
+#[derive(Serialize)]
+struct RoiJson {
+    region: Option<RegionJson>,
+    pore_pixels: usize,
+    material_pixels: usize,
+    density: Option<f64>,
+    pore_count: usize,
+    min_pore_area: Option<usize>,
+    max_pore_area: Option<usize>,
+    mean_pore_area: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct RipleyJson {
+    radius: f64,
+    k: f64,
+    l: f64,
+}
+
+#[derive(Serialize)]
+struct SpatialStatisticsJson {
+    pore_count: usize,
+    mean_nearest_neighbour_distance: f64,
+    std_nearest_neighbour_distance: f64,
+    mean_spacing: f64,
+    clark_evans_index: f64,
+    ripley: Vec<RipleyJson>,
+}
+
+#[derive(Serialize)]
+struct DepthBandJson {
+    start: f64,
+    end: f64,
+    pore_pixels: usize,
+    material_pixels: usize,
+    porosity: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct GroundTruthJson {
+    path: Option<String>,
+    precision: Option<f64>,
+    recall: Option<f64>,
+    iou: Option<f64>,
+    dice: Option<f64>,
+    pore_fraction_error: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct PoreJson {
+    label: u32,
+    area: usize,
+    centroid_x: f64,
+    centroid_y: f64,
+    excluded: bool,
+}
+
+impl PoreJson {
+    fn new(pore: &Pore, excluded: bool) -> Self {
+        Self {
+            label: pore.label,
+            area: pore.area,
+            centroid_x: pore.centroid.x,
+            centroid_y: pore.centroid.y,
+            excluded,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ImageJson {
+    file_name: String,
+    path: String,
+    fingerprint: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    parameters: ParametersJson,
+    analyzed: bool,
+    density: Option<f64>,
+    density_uncertainty: Option<UncertaintyJson>,
+    roi: Option<RoiJson>,
+    spatial_statistics: Option<SpatialStatisticsJson>,
+    depth_profile: Option<Vec<DepthBandJson>>,
+    ground_truth: Option<GroundTruthJson>,
+    pores: Option<Vec<PoreJson>>,
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+impl ImageJson {
+    fn new(image: &ImageData, uncertainty_levels: i16) -> Self {
+        let path = image.path.as_deref().unwrap_or(Path::new(""));
+        let dimensions = image.dimensions.or_else(|| {
+            let green_pixels = image.green_pixels.as_ref()?;
+            Some((green_pixels.width(), green_pixels.height()))
+        });
+        let parameters = image.analysis_parameters();
+        let region = dimensions.and_then(|(_, height)| parameters.image_region(height));
+
+        // only report the uncertainty if the sweep was done with the current parameters
+        let density_uncertainty = match &image.threshold_sweep {
+            Some(sweep) if sweep.matches(&parameters) => sweep
+                .uncertainty(image.threshold, uncertainty_levels)
+                .map(|density| UncertaintyJson {
+                    density,
+                    grey_levels: uncertainty_levels,
+                }),
+            _ => None,
+        };
+
+        let roi = match (&image.green_pixels, &image.white_pixels, &image.pores) {
+            (Some(green_pixels), Some(white_pixels), Some(pores)) => {
+                let areas = pores.iter().map(|pore| pore.area);
+                Some(RoiJson {
+                    region: RegionJson::new(region),
+                    pore_pixels: green_pixels.count(),
+                    material_pixels: white_pixels.count(),
+                    density: image.density,
+                    pore_count: pores.len(),
+                    min_pore_area: areas.clone().min(),
+                    max_pore_area: areas.clone().max(),
+                    mean_pore_area: (!pores.is_empty())
+                        .then(|| areas.sum::<usize>() as f64 / pores.len() as f64),
+                })
+            }
+            _ => None,
+        };
+
+        let spatial_statistics =
+            image
+                .spatial_statistics
+                .as_ref()
+                .map(|statistics| SpatialStatisticsJson {
+                    pore_count: statistics.pore_count,
+                    mean_nearest_neighbour_distance: statistics.mean_nearest_neighbour_distance,
+                    std_nearest_neighbour_distance: statistics.std_nearest_neighbour_distance,
+                    mean_spacing: statistics.mean_spacing,
+                    clark_evans_index: statistics.clark_evans_index,
+                    ripley: image
+                        .ripley()
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|point| RipleyJson {
+                            radius: point.radius,
+                            k: point.k,
+                            l: point.l,
+                        })
+                        .collect(),
+                });
+
+        let depth_profile = image.depth_profile.as_ref().map(|bands| {
+            bands
+                .iter()
+                .map(|band| DepthBandJson {
+                    start: band.start,
+                    end: band.end,
+                    pore_pixels: band.pore_pixels,
+                    material_pixels: band.material_pixels,
+                    porosity: band.porosity(),
+                })
+                .collect()
+        });
+
+        let ground_truth = image.segmentation_comparison.as_ref().map(|comparison| {
+            let metrics = &comparison.metrics;
+            GroundTruthJson {
+                path: image.ground_truth_path.as_deref().map(path_string),
+                precision: metrics.precision(),
+                recall: metrics.recall(),
+                iou: metrics.iou(),
+                dice: metrics.dice(),
+                pore_fraction_error: metrics.pore_fraction_error(),
+            }
+        });
+
+        let pores = image.pores.as_ref().map(|pores| {
+            let excluded = image.matched_excluded_pores.iter().flatten();
+            pores
+                .iter()
+                .map(|pore| PoreJson::new(pore, false))
+                .chain(excluded.map(|pore| PoreJson::new(pore, true)))
+                .collect()
+        });
+
+        Self {
+            file_name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: path_string(path),
+            fingerprint: file_fingerprint(path).map(|fingerprint| format!("{:016x}", fingerprint)),
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+            parameters: ParametersJson {
+                threshold: image.threshold,
+                minimal_pore_size_low: image.minimal_pore_size_low,
+                minimal_pore_size_high: image.minimal_pore_size_high,
+                included_min_feature_size: image.included_min_feature_size,
+                region: RegionJson::new(region),
+                manual_pixels: image.manual_overrides.len(),
+                excluded_pores: image.excluded_pores.len(),
+            },
+            analyzed: image.pores.is_some(),
+            density: image.density,
+            density_uncertainty,
+            roi,
+            spatial_statistics,
+            depth_profile,
+            ground_truth,
+            pores,
+        }
+    }
+}
+
+// the results of all images as described by `RESULTS_SCHEMA`
+pub fn results_json(app: &PoreDetectionApp) -> ResultsJson {
+    ResultsJson {
+        schema: RESULTS_SCHEMA_FILE,
+        schema_version: RESULTS_SCHEMA_VERSION,
+        project: ProjectJson {
+            path: app.project_path.as_deref().map(path_string),
+            project_version: PROJECT_VERSION,
+            image_count: app.images.images.len(),
+        },
+        provenance: ProvenanceJson {
+            program: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            exported_at: export_timestamp(),
+        },
+        calibration: CalibrationJson {
+            unit: "px",
+            pixel_size: 1.0,
+        },
+        images: app
+            .images
+            .images
+            .iter()
+            .map(|image| ImageJson::new(image, app.threshold_sweep.uncertainty_levels))
+            .collect(),
+    }
+}
+
+// the schema is saved next to the results, so `$schema` can be resolved
+pub fn export_json(app: &PoreDetectionApp) -> Result<(), String> {
+    let Some(path) = FileDialog::new().add_filter("JSON", &["json"]).save_file() else {
+        return Ok(());
+    };
+
+    let text = serde_json::to_string_pretty(&results_json(app))
+        .map_err(|err| format!("Could not write {:?}: {}", path, err))?;
+    std::fs::write(&path, text + "\n")
+        .map_err(|err| format!("Could not write {:?}: {}", path, err))?;
+
+    let schema_path = path.with_file_name(RESULTS_SCHEMA_FILE);
+    std::fs::write(&schema_path, RESULTS_SCHEMA)
+        .map_err(|err| format!("Could not write {:?}: {}", schema_path, err))
+}
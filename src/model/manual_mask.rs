@@ -0,0 +1,129 @@
+use core::fmt;
+use std::{collections::BTreeMap, str::FromStr};
+
+use egui_plot::PlotPoint;
+
+use super::pixel_mask::PixelMask;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Deserialize, serde::Serialize)]
+pub enum ManualOverride {
+    Pore,
+    Material,
+}
+
+impl fmt::Display for ManualOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManualOverride::Pore => write!(f, "Pore"),
+            ManualOverride::Material => write!(f, "Material"),
+        }
+    }
+}
+
+impl FromStr for ManualOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pore" => Ok(ManualOverride::Pore),
+            "Material" => Ok(ManualOverride::Material),
+            _ => Err(format!("unknown manual override: {}", s)),
+        }
+    }
+}
+
+// manually painted pixels in image coordinates (x, y)
+pub type ManualOverrides = BTreeMap<(u32, u32), ManualOverride>;
+
+pub fn paint_circle(
+    overrides: &mut ManualOverrides,
+    center: PlotPoint,
+    radius: u32,
+    value: ManualOverride,
+    width: u32,
+    height: u32,
+) {
+    let radius = radius as i64;
+    let (center_x, center_y) = (center.x.floor() as i64, center.y.floor() as i64);
+
+    for y in (center_y - radius)..=(center_y + radius) {
+        for x in (center_x - radius)..=(center_x + radius) {
+            if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                continue;
+            }
+
+            if (x - center_x).pow(2) + (y - center_y).pow(2) <= radius.pow(2) {
+                overrides.insert((x as u32, y as u32), value);
+            }
+        }
+    }
+}
+
+// replaces the automatic result for every overridden pixel inside the analyzed area
+pub fn apply_manual_overrides(
+    green_pixels: &mut PixelMask,
+    white_pixels: &mut PixelMask,
+    overrides: &ManualOverrides,
+    region: Option<(u32, u32, u32, u32)>,
+) {
+    for (&(x, y), value) in overrides {
+        if x >= green_pixels.width() || y >= green_pixels.height() {
+            continue;
+        }
+
+        green_pixels.set(x, y, false);
+        white_pixels.set(x, y, false);
+
+        if let Some((x_start, x_end, y_start, y_end)) = region {
+            if x < x_start || x > x_end || y < y_start || y > y_end {
+                continue;
+            }
+        }
+
+        match value {
+            ManualOverride::Pore => green_pixels.set(x, y, true),
+            ManualOverride::Material => white_pixels.set(x, y, true),
+        }
+    }
+}
+
+// groups the overrides into vertical runs (x, y start, y end, value) so they can be stored compactly
+pub fn override_runs(overrides: &ManualOverrides) -> Vec<(u32, u32, u32, ManualOverride)> {
+    let mut runs: Vec<(u32, u32, u32, ManualOverride)> = Vec::new();
+
+    for (&(x, y), &value) in overrides {
+        match runs.last_mut() {
+            Some((run_x, _, run_y_end, run_value))
+                if *run_x == x && *run_y_end + 1 == y && *run_value == value =>
+            {
+                *run_y_end = y;
+            }
+            _ => runs.push((x, y, y, value)),
+        }
+    }
+
+    runs
+}
+
+// the inverse of `override_runs`, runs from a file are checked so a wrong cell can't insert billions of pixels
+pub fn insert_override_run(
+    overrides: &mut ManualOverrides,
+    (x, y_start, y_end, value): (u32, u32, u32, ManualOverride),
+    (width, height): (u32, u32),
+) -> Result<(), String> {
+    if y_end < y_start {
+        return Err(format!("Y End {} is before Y Start {}", y_end, y_start));
+    }
+    if x >= width || y_end >= height {
+        return Err(format!(
+            "the run at x {} from y {} to {} is outside of the image ({} × {})",
+            x, y_start, y_end, width, height
+        ));
+    }
+
+    for y in y_start..=y_end {
+        overrides.insert((x, y), value);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use image::{DynamicImage, GrayImage, Luma};
+use tiff::{
+    decoder::{Decoder, DecodingResult},
+    encoder::{colortype, TiffEncoder},
+};
+
+use super::{
+    detection_app::PoreDetectionApp,
+    image_data::ImageData,
+    manual_mask::{ManualOverride, ManualOverrides},
+    overlay::export_file_name,
+    pixel_mask::PixelMask,
+};
+
+pub const PORE_MASK_SUFFIX: &str = "pores";
+pub const MATERIAL_MASK_SUFFIX: &str = "material";
+pub const LABEL_MAP_SUFFIX: &str = "labels";
+
+// white marks a set pixel, like the ground truth masks
+fn mask_image(mask: &PixelMask) -> GrayImage {
+    let mut image = GrayImage::new(mask.width(), mask.height());
+    mask.iter()
+        .for_each(|(x, y)| image.put_pixel(x, y, Luma([255])));
+
+    image
+}
+
+// the label of every pixel of an accepted pore, the same as in the per-pore sheet and the JSON export,
+// filled features and manual pores have no label and are 0 like the background
+fn label_map(image: &ImageData) -> Result<Vec<u32>, String> {
+    let (Some(green_pixels), Some(pores)) = (&image.green_pixels, &image.pores) else {
+        return Err("the image is not analyzed".to_string());
+    };
+    let labels = image
+        .label_image()
+        .ok_or("the pixel groups of the image are not available".to_string())?;
+
+    let accepted = pores.iter().map(|pore| pore.label).collect::<HashSet<_>>();
+    let width = green_pixels.width() as usize;
+    let mut map = vec![0; width * green_pixels.height() as usize];
+    green_pixels.iter().for_each(|(x, y)| {
+        let label = labels.get_pixel(x, y)[0];
+        if accepted.contains(&label) {
+            map[y as usize * width + x as usize] = label;
+        }
+    });
+
+    Ok(map)
+}
+
+// 16 bit if all labels fit, most tools can't read 32 bit images
+fn write_label_tiff(path: &Path, width: u32, height: u32, map: &[u32]) -> Result<(), String> {
+    let file = File::create(path).map_err(|err| format!("Could not write {:?}: {}", path, err))?;
+    let mut encoder = TiffEncoder::new(BufWriter::new(file))
+        .map_err(|err| format!("Could not write {:?}: {}", path, err))?;
+
+    let result = if map.iter().all(|label| *label <= u16::MAX as u32) {
+        let map = map.iter().map(|label| *label as u16).collect::<Vec<_>>();
+        encoder.write_image::<colortype::Gray16>(width, height, &map)
+    } else {
+        encoder.write_image::<colortype::Gray32>(width, height, map)
+    };
+
+    result.map_err(|err| format!("Could not write {:?}: {}", path, err))
+}
+
+// `<name>_pores.png`, `<name>_material.png` and `<name>_labels.tif` in `folder`, `name` is from `export_names`
+// nothing is written if one of them fails, `Ok(Some(warning))` if a large image only got its masks
+pub fn export_masks(
+    image: &ImageData,
+    name: &str,
+    folder: &Path,
+) -> Result<Option<String>, String> {
+    let (Some(green_pixels), Some(white_pixels)) = (&image.green_pixels, &image.white_pixels)
+    else {
+        return Err("the image is not analyzed".to_string());
+    };
+    // the pixel groups of large images are not kept
+    let map = match image.is_large() {
+        true => None,
+        false => Some(label_map(image)?),
+    };
+
+    for (mask, suffix) in [
+        (green_pixels, PORE_MASK_SUFFIX),
+        (white_pixels, MATERIAL_MASK_SUFFIX),
+    ] {
+        let path = folder.join(export_file_name(name, suffix, "png"));
+        mask_image(mask)
+            .save(&path)
+            .map_err(|err| format!("Could not write {:?}: {}", path, err))?;
+    }
+
+    let Some(map) = map else {
+        return Ok(Some(
+            "the label map is skipped, the pixel groups of large images are not kept".to_string(),
+        ));
+    };
+    let path = folder.join(export_file_name(name, LABEL_MAP_SUFFIX, "tif"));
+    write_label_tiff(&path, green_pixels.width(), green_pixels.height(), &map)?;
+
+    Ok(None)
+}
+
+// bright pixels of 8 bit masks and all non-zero pixels of 16 and 32 bit label maps are set
+pub fn load_mask(path: &Path) -> Result<PixelMask, String> {
+    let error = |err: &dyn std::fmt::Display| format!("Could not open {:?}: {}", path, err);
+
+    let (width, height, is_set): (u32, u32, Box<dyn Fn(usize) -> bool>) = match image::open(path) {
+        Ok(DynamicImage::ImageLuma16(image)) => {
+            let (width, height) = image.dimensions();
+            let data = image.into_raw();
+            (width, height, Box::new(move |i| data[i] != 0))
+        }
+        Ok(image) => {
+            let image = image.to_luma8();
+            let (width, height) = image.dimensions();
+            let data = image.into_raw();
+            (width, height, Box::new(move |i| data[i] > 127))
+        }
+        // the image crate can't read 32 bit grey images
+        Err(err) => {
+            let file = File::open(path).map_err(|err| error(&err))?;
+            let mut decoder = Decoder::new(file).map_err(|_| error(&err))?;
+            let (width, height) = decoder.dimensions().map_err(|err| error(&err))?;
+            match decoder.read_image().map_err(|err| error(&err))? {
+                DecodingResult::U32(data) => (width, height, Box::new(move |i| data[i] != 0)),
+                _ => return Err(error(&err)),
+            }
+        }
+    };
+
+    let mut mask = PixelMask::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            if is_set(y as usize * width as usize + x as usize) {
+                mask.set(x, y, true);
+            }
+        }
+    }
+
+    Ok(mask)
+}
+
+// `<name>_material.png` next to `<name>_pores.png` or `<name>_labels.tif`
+fn material_mask_path(path: &Path) -> Option<PathBuf> {
+    let stem = path.file_stem()?.to_string_lossy().to_string();
+    let name = [PORE_MASK_SUFFIX, LABEL_MAP_SUFFIX]
+        .iter()
+        .find_map(|suffix| stem.strip_suffix(&format!("_{}", suffix)))?;
+    let material_path = path.with_file_name(format!("{}_{}.png", name, MATERIAL_MASK_SUFFIX));
+
+    material_path.exists().then_some(material_path)
+}
+
+// the manual edits that make the analysis reproduce the masks inside the analyzed region and the number of
+// pixels it can't reproduce: there is no manual edit for pixels that are neither pore nor material (like
+// dark groups outside of the size range), so pixels that are in neither imported mask keep the automatic result
+pub fn mask_overrides(
+    (green_pixels, white_pixels): (&PixelMask, &PixelMask),
+    region: Option<(u32, u32, u32, u32)>,
+    pores: &PixelMask,
+    material: Option<&PixelMask>,
+) -> (ManualOverrides, usize) {
+    let (width, height) = (green_pixels.width(), green_pixels.height());
+    let (x_start, x_end, y_start, y_end) = region.unwrap_or((0, width - 1, 0, height - 1));
+
+    let mut changes = ManualOverrides::new();
+    let mut unreproduced = 0;
+    for y in y_start..=y_end.min(height - 1) {
+        for x in x_start..=x_end.min(width - 1) {
+            let current = if green_pixels.get(x, y) {
+                Some(ManualOverride::Pore)
+            } else if white_pixels.get(x, y) {
+                Some(ManualOverride::Material)
+            } else {
+                None
+            };
+
+            // without a material mask only pores that are not in the pore mask become material
+            let target = match material {
+                _ if pores.get(x, y) => Some(ManualOverride::Pore),
+                Some(material) => material.get(x, y).then_some(ManualOverride::Material),
+                None => (current == Some(ManualOverride::Pore)).then_some(ManualOverride::Material),
+            };
+
+            match target {
+                Some(target) if Some(target) != current => {
+                    changes.insert((x, y), target);
+                }
+                None if current.is_some() => unreproduced += 1,
+                _ => {}
+            }
+        }
+    }
+
+    (changes, unreproduced)
+}
+
+// the pixels that differ from the current result become manual edits, see `mask_overrides`,
+// returns the number of changed pixels and of pixels that could not be reproduced
+pub fn import_masks(
+    app: &mut PoreDetectionApp,
+    index: usize,
+    path: &Path,
+) -> Result<(usize, usize), String> {
+    let pores = load_mask(path)?;
+    let material = material_mask_path(path)
+        .map(|path| load_mask(&path))
+        .transpose()?;
+
+    let image = &mut app.images.images[index];
+    let (Some(green_pixels), Some(white_pixels)) = (&image.green_pixels, &image.white_pixels)
+    else {
+        return Err("The image has to be analyzed before a mask can be imported.".to_string());
+    };
+    let size = (green_pixels.width(), green_pixels.height());
+    if (pores.width(), pores.height()) != size
+        || material
+            .as_ref()
+            .is_some_and(|material| (material.width(), material.height()) != size)
+    {
+        return Err("The mask needs to have the same size as the image.".to_string());
+    }
+
+    // edits outside of the region are ignored by the analysis
+    let (changes, unreproduced) = mask_overrides(
+        (green_pixels, white_pixels),
+        image.analysis_parameters().image_region(size.1),
+        &pores,
+        material.as_ref(),
+    );
+    log::info!(
+        "Imported mask {:?}, {} pixels changed, {} pixels can't be reproduced",
+        path,
+        changes.len(),
+        unreproduced
+    );
+
+    let before = image.manual_overrides.clone();
+    let changed = changes.len();
+    image.manual_overrides.extend(changes);
+    app.history
+        .record_mask_edit(&app.images.images[index], &before, "Import mask");
+    app.reload_image(Some(index));
+
+    Ok((changed, unreproduced))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use image::{GrayImage, Luma};
+
+    use super::{export_masks, load_mask, mask_overrides, MATERIAL_MASK_SUFFIX, PORE_MASK_SUFFIX};
+    use crate::model::{
+        image_data::{analyze_grayscale, AnalysisParameters, ImageData},
+        job::JobControl,
+        manual_mask::ManualOverrides,
+        overlay::export_file_name,
+        pixel_mask::PixelMask,
+    };
+
+    const PARAMETERS: AnalysisParameters = AnalysisParameters {
+        threshold: 100,
+        minimal_pore_size_low: 5.0,
+        minimal_pore_size_high: 1000.0,
+        included_min_feature_size: 0.0,
+        region_start: None,
+        region_end: None,
+    };
+
+    // two pores and a dark group that is too small to be one, so it is neither pore nor material
+    fn synthetic_image() -> GrayImage {
+        GrayImage::from_fn(40, 40, |x, y| {
+            let dark = (5..10).contains(&x) && (5..10).contains(&y)
+                || (20..22).contains(&x) && (20..22).contains(&y)
+                || (28..34).contains(&x) && (28..34).contains(&y);
+            Luma([if dark { 20 } else { 200 }])
+        })
+    }
+
+    fn analyzed_image(grayscale: &GrayImage, overrides: &ManualOverrides) -> ImageData {
+        let result = analyze_grayscale(
+            grayscale,
+            &PARAMETERS,
+            overrides,
+            &[],
+            &JobControl::default(),
+        )
+        .unwrap();
+        let mut image = ImageData {
+            dimensions: Some(grayscale.dimensions()),
+            ..Default::default()
+        };
+        image.apply_analysis_result(result, 1.0);
+
+        image
+    }
+
+    fn export_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!(
+            "pore_detection_mask_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&folder).unwrap();
+
+        folder
+    }
+
+    fn mask_path(folder: &Path, suffix: &str) -> PathBuf {
+        folder.join(export_file_name("image", suffix, "png"))
+    }
+
+    // sets or clears a rectangle in an exported mask
+    fn edit_mask(path: &Path, xs: std::ops::Range<u32>, ys: std::ops::Range<u32>, set: bool) {
+        let mut mask = image::open(path).unwrap().to_luma8();
+        for y in ys {
+            for x in xs.clone() {
+                mask.put_pixel(x, y, Luma([if set { 255 } else { 0 }]));
+            }
+        }
+        mask.save(path).unwrap();
+    }
+
+    fn import(image: &ImageData, folder: &Path) -> (ManualOverrides, usize, PixelMask, PixelMask) {
+        let pores = load_mask(&mask_path(folder, PORE_MASK_SUFFIX)).unwrap();
+        let material = load_mask(&mask_path(folder, MATERIAL_MASK_SUFFIX)).unwrap();
+        let (overrides, unreproduced) = mask_overrides(
+            (
+                image.green_pixels.as_ref().unwrap(),
+                image.white_pixels.as_ref().unwrap(),
+            ),
+            None,
+            &pores,
+            Some(&material),
+        );
+
+        (overrides, unreproduced, pores, material)
+    }
+
+    #[test]
+    fn exported_masks_are_imported_without_changes() {
+        let grayscale = synthetic_image();
+        let image = analyzed_image(&grayscale, &Default::default());
+        let folder = export_folder("unchanged");
+
+        assert_eq!(export_masks(&image, "image", &folder), Ok(None));
+        let (overrides, unreproduced, pores, material) = import(&image, &folder);
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        assert!(overrides.is_empty());
+        assert_eq!(unreproduced, 0);
+        assert!(&pores == image.green_pixels.as_ref().unwrap());
+        assert!(&material == image.white_pixels.as_ref().unwrap());
+    }
+
+    #[test]
+    fn edited_masks_are_reproduced_by_the_analysis() {
+        let grayscale = synthetic_image();
+        let image = analyzed_image(&grayscale, &Default::default());
+        let folder = export_folder("edited");
+        export_masks(&image, "image", &folder).unwrap();
+
+        // the first pore becomes material and a new pore is drawn into the material
+        edit_mask(&mask_path(&folder, PORE_MASK_SUFFIX), 5..10, 5..10, false);
+        edit_mask(
+            &mask_path(&folder, MATERIAL_MASK_SUFFIX),
+            5..10,
+            5..10,
+            true,
+        );
+        edit_mask(&mask_path(&folder, PORE_MASK_SUFFIX), 12..16, 30..33, true);
+        edit_mask(
+            &mask_path(&folder, MATERIAL_MASK_SUFFIX),
+            12..16,
+            30..33,
+            false,
+        );
+
+        let (overrides, unreproduced, pores, material) = import(&image, &folder);
+        std::fs::remove_dir_all(&folder).unwrap();
+        assert_eq!(overrides.len(), 25 + 12);
+        assert_eq!(unreproduced, 0);
+
+        let reanalyzed = analyzed_image(&grayscale, &overrides);
+        assert!(&pores == reanalyzed.green_pixels.as_ref().unwrap());
+        assert!(&material == reanalyzed.white_pixels.as_ref().unwrap());
+    }
+
+    #[test]
+    fn pixels_in_neither_mask_are_counted() {
+        let grayscale = synthetic_image();
+        let image = analyzed_image(&grayscale, &Default::default());
+        let folder = export_folder("neither");
+        export_masks(&image, "image", &folder).unwrap();
+
+        // the second pore is removed from the pore mask only
+        edit_mask(&mask_path(&folder, PORE_MASK_SUFFIX), 28..34, 28..34, false);
+
+        let (overrides, unreproduced, _, _) = import(&image, &folder);
+        std::fs::remove_dir_all(&folder).unwrap();
+        assert!(overrides.is_empty());
+        assert_eq!(unreproduced, 36);
+    }
+}
@@ -1,3 +1,26 @@
+pub mod autosave;
+pub mod batch;
+pub mod csv;
+pub mod depth_profile;
 pub mod detection_app;
+pub mod excel_export;
+pub mod excel_import;
+pub mod excluded_pores;
+pub mod ground_truth;
+pub mod histogram;
+pub mod history;
 pub mod image_data;
 pub mod images;
+pub mod job;
+pub mod json_export;
+pub mod manual_mask;
+pub mod mask_export;
+pub mod overlay;
+pub mod pipeline;
+pub mod pixel_mask;
+pub mod preview;
+pub mod project;
+pub mod relink;
+pub mod spatial_statistics;
+pub mod threshold_sweep;
+pub mod tiled;
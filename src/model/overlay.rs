@@ -0,0 +1,363 @@
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{FontArc, PxScale};
+use image::{
+    codecs::jpeg::JpegEncoder, imageops, DynamicImage, ImageFormat, Pixel, Rgb, RgbImage, Rgba,
+    RgbaImage,
+};
+use imageproc::{
+    drawing::{draw_filled_rect_mut, draw_hollow_rect_mut, draw_text_mut, text_size},
+    rect::Rect,
+};
+
+use crate::view::{
+    display_settings_window::{OverlaySettings, PoreClass, PoreColoring},
+    image_export_window::{ExportImageFormat, ImageExportSettings, ImageLayout},
+};
+
+use super::{
+    ground_truth::SegmentationComparison,
+    image_data::{ImageData, LabelImage},
+    manual_mask::ManualOverride,
+    pixel_mask::PixelMask,
+};
+
+const ROI_COLOR: Rgb<u8> = Rgb([255, 220, 0]);
+const CAPTION_BACKGROUND: Rgb<u8> = Rgb([0, 0, 0]);
+const CAPTION_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+
+// the plot and the exported overlay both mix the overlay colours into the original this way
+pub fn blend_pixel(original: &mut Rgba<u8>, overlay: Rgba<u8>) {
+    original.blend(&overlay);
+}
+
+// draws the material and the pores like the plot shows them, `put_pixel` decides how the colours are combined
+// the labels are only needed to colour the pores by size or class
+pub fn draw_overlay(
+    current_image: &ImageData,
+    (green_pixels, white_pixels): (&PixelMask, &PixelMask),
+    labels: Option<&LabelImage>,
+    settings: &OverlaySettings,
+    pore_coloring: PoreColoring,
+    comparison: Option<&SegmentationComparison>,
+    mut put_pixel: impl FnMut(u32, u32, Rgba<u8>),
+) {
+    if settings.show_material {
+        let white_pixel = settings.material_pixel();
+        white_pixels.iter().for_each(|(x, y)| {
+            put_pixel(x, y, white_pixel);
+        });
+    }
+
+    if settings.show_pores {
+        let (width, height) = (green_pixels.width(), green_pixels.height());
+
+        // a pixel is on the outline if one of its direct neighbours is not a pore
+        let is_outline = |x: u32, y: u32| {
+            x == 0
+                || y == 0
+                || x == width - 1
+                || y == height - 1
+                || !green_pixels.get(x - 1, y)
+                || !green_pixels.get(x + 1, y)
+                || !green_pixels.get(x, y - 1)
+                || !green_pixels.get(x, y + 1)
+        };
+
+        let size_range = current_image.pore_size_range().unwrap_or((1, 1));
+        let label_at = |x: u32, y: u32| labels.map_or(0, |labels| labels.get_pixel(x, y)[0]);
+
+        green_pixels.iter().for_each(|(x, y)| {
+            if settings.outline_only && !is_outline(x, y) {
+                return;
+            }
+
+            let green_pixel = match pore_coloring {
+                PoreColoring::Uniform => settings.pore_pixel(),
+                PoreColoring::BySize => {
+                    let label = label_at(x, y);
+                    match &current_image.label_sizes {
+                        Some(label_sizes) if label > 0 => {
+                            settings.size_pixel(label_sizes[label as usize], size_range)
+                        }
+                        _ => settings.pore_pixel(),
+                    }
+                }
+                PoreColoring::ByClass => {
+                    let class = if current_image.manual_overrides.get(&(x, y))
+                        == Some(&ManualOverride::Pore)
+                    {
+                        PoreClass::Manual
+                    } else if labels.is_some() && label_at(x, y) == 0 {
+                        PoreClass::FilledFeature
+                    } else {
+                        PoreClass::Detected
+                    };
+                    settings.class_pixel(class)
+                }
+            };
+
+            put_pixel(x, y, green_pixel);
+        });
+    }
+
+    // false positives in red and false negatives in blue on top of the segmentation
+    if let Some(comparison) = comparison {
+        let false_positive_pixel = Rgba([255, 0, 0, 230]);
+        let false_negative_pixel = Rgba([0, 80, 255, 230]);
+
+        comparison.false_positive_pixels.iter().for_each(|(x, y)| {
+            put_pixel(x, y, false_positive_pixel);
+        });
+
+        comparison.false_negative_pixels.iter().for_each(|(x, y)| {
+            put_pixel(x, y, false_negative_pixel);
+        });
+    }
+}
+
+// the font egui uses for its text, so no font has to be shipped with the program
+fn caption_font() -> Option<FontArc> {
+    let fonts = egui::FontDefinitions::default();
+    let font = fonts.font_data.get("Ubuntu-Light")?;
+    FontArc::try_from_vec(font.font.to_vec()).ok()
+}
+
+// the longest length of 1, 2 or 5 times a power of ten that fits into `max_length`
+fn scale_bar_length(max_length: u32) -> u32 {
+    let mut length = 1;
+    let mut power = 1u64;
+    while power <= max_length as u64 {
+        for factor in [1, 2, 5] {
+            if factor * power <= max_length as u64 {
+                length = factor * power;
+            }
+        }
+        power *= 10;
+    }
+
+    length as u32
+}
+
+// the original with the overlay blended on top, the result has to be analyzed before
+fn overlay_image(
+    image: &ImageData,
+    original: &RgbaImage,
+    overlay: &OverlaySettings,
+    ground_truth_overlay: bool,
+) -> Option<RgbaImage> {
+    let green_pixels = image.green_pixels.as_ref()?;
+    let white_pixels = image.white_pixels.as_ref()?;
+    let comparison = image
+        .segmentation_comparison
+        .as_ref()
+        .filter(|_| ground_truth_overlay);
+    // only the selected image keeps its labels
+    let labels = match overlay.pore_coloring {
+        PoreColoring::Uniform => None,
+        PoreColoring::BySize | PoreColoring::ByClass => image.label_image(),
+    };
+
+    let mut result = original.clone();
+    draw_overlay(
+        image,
+        (green_pixels, white_pixels),
+        labels.as_deref(),
+        overlay,
+        overlay.pore_coloring,
+        comparison,
+        |x, y, pixel| blend_pixel(result.get_pixel_mut(x, y), pixel),
+    );
+
+    Some(result)
+}
+
+// renders the image like it is shown in the plot without the GPU, `Err` if there is nothing to draw
+pub fn render_image(
+    image: &ImageData,
+    overlay: &OverlaySettings,
+    ground_truth_overlay: bool,
+    settings: &ImageExportSettings,
+) -> Result<RgbImage, String> {
+    // a full size copy of a large image would take several gigabytes
+    if image.is_large() {
+        return Err("the image is too large to be rendered".to_string());
+    }
+    let original = image
+        .image
+        .as_ref()
+        .ok_or("the image is not loaded".to_string())?
+        .to_rgba8();
+    let (width, height) = original.dimensions();
+
+    let overlay_image = || {
+        overlay_image(image, &original, overlay, ground_truth_overlay)
+            .ok_or("the image is not analyzed".to_string())
+    };
+    let panels = match settings.layout {
+        ImageLayout::Original => vec![original.clone()],
+        ImageLayout::Overlay => vec![overlay_image()?],
+        ImageLayout::SideBySide => vec![original.clone(), overlay_image()?],
+    };
+
+    let font = caption_font();
+    let font_size = (height as f32 / 30.0).max(16.0);
+    let caption_height = match (&font, settings.caption || settings.scale_bar) {
+        (Some(_), true) => (font_size * 1.6).round() as u32,
+        _ => 0,
+    };
+
+    let mut result = RgbImage::from_pixel(
+        width * panels.len() as u32,
+        height + caption_height,
+        CAPTION_BACKGROUND,
+    );
+    for (i, panel) in panels.iter().enumerate() {
+        let panel = DynamicImage::ImageRgba8(panel.clone()).to_rgb8();
+        imageops::replace(&mut result, &panel, (i as u32 * width) as i64, 0);
+    }
+
+    if settings.roi_outline {
+        let region = image.analysis_parameters().image_region(height);
+        if let Some((x_start, x_end, y_start, y_end)) = region {
+            // at least two pixels wide, so the outline is still visible in a scaled down report
+            let thickness = (width.max(height) / 500).max(2);
+            for i in 0..panels.len() as u32 {
+                for inset in 0..thickness {
+                    let rect_width = (x_end + 1).saturating_sub(x_start + 2 * inset);
+                    let rect_height = (y_end + 1).saturating_sub(y_start + 2 * inset);
+                    if rect_width == 0 || rect_height == 0 {
+                        break;
+                    }
+                    draw_hollow_rect_mut(
+                        &mut result,
+                        Rect::at(
+                            (i * width + x_start + inset) as i32,
+                            (y_start + inset) as i32,
+                        )
+                        .of_size(rect_width, rect_height),
+                        ROI_COLOR,
+                    );
+                }
+            }
+        }
+    }
+
+    let Some(font) = font.filter(|_| caption_height > 0) else {
+        return Ok(result);
+    };
+    let scale = PxScale::from(font_size);
+    let text_y = (height + (caption_height - font_size as u32) / 2) as i32;
+    let margin = font_size as u32 / 2;
+
+    if settings.caption {
+        let name = image
+            .path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let caption = match image.density {
+            Some(density) => format!("{}    Density: {:.2} %", name, density),
+            None => name,
+        };
+        draw_text_mut(
+            &mut result,
+            CAPTION_COLOR,
+            margin as i32,
+            text_y,
+            scale,
+            &font,
+            &caption,
+        );
+    }
+
+    // there is no calibration yet, so the bar is measured in pixels of the image
+    if settings.scale_bar {
+        let length = scale_bar_length(width / 5);
+        let label = format!("{} px", length);
+        let (label_width, _) = text_size(scale, &font, &label);
+        let bar_height = (font_size / 4.0).round().max(2.0) as u32;
+        let bar_x = result.width().saturating_sub(margin + length);
+        let bar_y = height + (caption_height - bar_height) / 2;
+
+        draw_filled_rect_mut(
+            &mut result,
+            Rect::at(bar_x as i32, bar_y as i32).of_size(length, bar_height),
+            CAPTION_COLOR,
+        );
+        draw_text_mut(
+            &mut result,
+            CAPTION_COLOR,
+            bar_x as i32 - (label_width + margin) as i32,
+            text_y,
+            scale,
+            &font,
+            &label,
+        );
+    }
+
+    Ok(result)
+}
+
+pub fn save_image(image: &RgbImage, path: &Path, format: ExportImageFormat) -> Result<(), String> {
+    let result = match format {
+        ExportImageFormat::Png => image.save_with_format(path, ImageFormat::Png),
+        ExportImageFormat::Tiff => image.save_with_format(path, ImageFormat::Tiff),
+        // the default quality blurs the outlines of small pores
+        ExportImageFormat::Jpeg => std::fs::File::create(path)
+            .map_err(image::ImageError::IoError)
+            .and_then(|file| {
+                JpegEncoder::new_with_quality(std::io::BufWriter::new(file), 95).encode_image(image)
+            }),
+    };
+
+    result.map_err(|err| format!("Could not write {:?}: {}", path, err))
+}
+
+// the name of every image in exports, images with the same file name (e.g. from different specimen folders)
+// get their folder as prefix and a counter if that is not enough, so exports into one folder never overwrite each other
+pub fn export_names(images: &[ImageData]) -> Vec<String> {
+    let stems = images
+        .iter()
+        .map(|image| {
+            image
+                .path
+                .as_deref()
+                .and_then(Path::file_stem)
+                .map_or("image".to_string(), |stem| {
+                    stem.to_string_lossy().to_string()
+                })
+        })
+        .collect::<Vec<_>>();
+
+    let mut names: Vec<String> = Vec::new();
+    for (image, stem) in images.iter().zip(&stems) {
+        // file systems may ignore the case
+        let is_shared = stems
+            .iter()
+            .filter(|other| other.eq_ignore_ascii_case(stem))
+            .count()
+            > 1;
+        let folder = image.path.as_deref().and_then(Path::parent);
+        let name = match folder.and_then(|folder| folder.file_name()) {
+            Some(folder) if is_shared => format!("{}_{}", folder.to_string_lossy(), stem),
+            _ => stem.clone(),
+        };
+
+        let mut unique = name.clone();
+        let mut i = 2;
+        while names.iter().any(|used| used.eq_ignore_ascii_case(&unique)) {
+            unique = format!("{}_{}", name, i);
+            i += 1;
+        }
+        names.push(unique);
+    }
+
+    names
+}
+
+// `<name>_<suffix>.<extension>` next to each other in one folder, `name` is from `export_names`
+pub fn export_file_name(name: &str, suffix: &str, extension: &str) -> PathBuf {
+    PathBuf::from(format!("{}_{}.{}", name, suffix, extension))
+}
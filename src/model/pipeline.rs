@@ -0,0 +1,149 @@
+use std::sync::{Arc, Mutex};
+
+use image::{GrayImage, Luma};
+use imageproc::definitions::{HasBlack, HasWhite};
+
+use super::{image_data::LabelImage, job::JobControl, preview::PreviewBase};
+
+// result of the threshold and both connected components passes, only depends on the threshold
+pub struct Labelling {
+    pub threshold: i16,
+    // groups of black pixels (pore candidates), 0 for white pixels
+    pub labels: Arc<LabelImage>,
+    pub label_sizes: Arc<Vec<usize>>,
+    // groups of white pixels (material), 0 for black pixels
+    pub black_labels: LabelImage,
+    pub black_label_sizes: Vec<usize>,
+}
+
+fn label_sizes(labels: &LabelImage) -> Vec<usize> {
+    let num_labels = labels.iter().max().unwrap_or(&0);
+    let mut sizes = vec![0; *num_labels as usize + 1];
+    labels.pixels().for_each(|p| {
+        sizes[p[0] as usize] += 1;
+    });
+
+    sizes
+}
+
+pub fn label_grayscale(
+    grayscale: &GrayImage,
+    threshold: i16,
+    control: &JobControl,
+) -> Option<Labelling> {
+    let grayscale_thresh = imageproc::contrast::threshold(
+        grayscale,
+        threshold.try_into().unwrap(),
+        imageproc::contrast::ThresholdType::Binary,
+    );
+
+    if control.is_cancelled() {
+        return None;
+    }
+
+    // find connected groups of black pixels
+    let labels = imageproc::region_labelling::connected_components(
+        &grayscale_thresh,
+        imageproc::region_labelling::Connectivity::Eight,
+        Luma::white(),
+    );
+    let pore_label_sizes = label_sizes(&labels);
+
+    if control.is_cancelled() {
+        return None;
+    }
+
+    // find connected groups of white pixels
+    let black_labels = imageproc::region_labelling::connected_components(
+        &grayscale_thresh,
+        imageproc::region_labelling::Connectivity::Eight,
+        Luma::black(),
+    );
+    let black_label_sizes = label_sizes(&black_labels);
+
+    if control.is_cancelled() {
+        return None;
+    }
+
+    Some(Labelling {
+        threshold,
+        labels: Arc::new(labels),
+        label_sizes: Arc::new(pore_label_sizes),
+        black_labels,
+        black_label_sizes,
+    })
+}
+
+#[derive(Default)]
+struct PipelineStages {
+    grayscale: Option<Arc<GrayImage>>,
+    labelling: Option<Arc<Labelling>>,
+    preview: Option<Arc<PreviewBase>>,
+}
+
+// cached stages of the analysis of one image, shared with its analysis threads
+// the size filters, region, manual edits and excluded pores are applied on top of the cached labelling
+#[derive(Clone, Default)]
+pub struct PipelineCache {
+    stages: Arc<Mutex<PipelineStages>>,
+}
+
+impl PipelineCache {
+    pub fn grayscale(&self) -> Option<Arc<GrayImage>> {
+        self.stages.lock().unwrap().grayscale.clone()
+    }
+
+    pub fn grayscale_or_insert_with(&self, f: impl FnOnce() -> GrayImage) -> Arc<GrayImage> {
+        if let Some(grayscale) = self.grayscale() {
+            return grayscale;
+        }
+
+        let grayscale = Arc::new(f());
+        self.stages.lock().unwrap().grayscale = Some(grayscale.clone());
+        grayscale
+    }
+
+    // only returns the labelling if it was computed for this threshold
+    pub fn labelling(&self, threshold: i16) -> Option<Arc<Labelling>> {
+        self.stages
+            .lock()
+            .unwrap()
+            .labelling
+            .clone()
+            .filter(|labelling| labelling.threshold == threshold)
+    }
+
+    // the lock is not held while labelling, so the ui is never blocked by it
+    pub fn labelling_or_insert(
+        &self,
+        grayscale: &GrayImage,
+        threshold: i16,
+        control: &JobControl,
+    ) -> Option<Arc<Labelling>> {
+        if let Some(labelling) = self.labelling(threshold) {
+            return Some(labelling);
+        }
+
+        let labelling = Arc::new(label_grayscale(grayscale, threshold, control)?);
+        self.stages.lock().unwrap().labelling = Some(labelling.clone());
+        Some(labelling)
+    }
+
+    pub fn preview(&self) -> Option<Arc<PreviewBase>> {
+        self.stages.lock().unwrap().preview.clone()
+    }
+
+    pub fn preview_or_insert_with(&self, f: impl FnOnce() -> PreviewBase) -> Arc<PreviewBase> {
+        if let Some(preview) = self.preview() {
+            return preview;
+        }
+
+        let preview = Arc::new(f());
+        self.stages.lock().unwrap().preview = Some(preview.clone());
+        preview
+    }
+
+    pub fn clear(&self) {
+        *self.stages.lock().unwrap() = PipelineStages::default();
+    }
+}
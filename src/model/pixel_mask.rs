@@ -0,0 +1,74 @@
+// one bit per pixel in image coordinates, row by row
+#[derive(Clone, PartialEq, Debug)]
+pub struct PixelMask {
+    width: u32,
+    height: u32,
+    words: Vec<u64>,
+}
+
+impl PixelMask {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            words: vec![0; (width as usize * height as usize).div_ceil(64)],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        let index = self.index(x, y);
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, value: bool) {
+        let index = self.index(x, y);
+        if value {
+            self.words[index / 64] |= 1 << (index % 64);
+        } else {
+            self.words[index / 64] &= !(1 << (index % 64));
+        }
+    }
+
+    // number of set pixels
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    // coordinates (x, y) of all set pixels, skipping empty words
+    pub fn iter(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let width = self.width as usize;
+
+        self.words
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| **word != 0)
+            .flat_map(move |(word_index, word)| {
+                let mut word = *word;
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+
+                    let index = word_index * 64 + bit;
+                    Some(((index % width) as u32, (index / width) as u32))
+                })
+            })
+    }
+}
@@ -0,0 +1,128 @@
+use egui_plot::PlotPoint;
+use image::{imageops, DynamicImage, GrayImage, Luma, RgbaImage};
+
+use super::{
+    excluded_pores::ExcludedPore,
+    image_data::{analyze_grayscale, AnalysisParameters, AnalysisResult},
+    job::JobControl,
+    manual_mask::ManualOverrides,
+    tiled::StripSource,
+};
+
+// longest side of the downsampled image used while a slider is dragged
+const PREVIEW_SIZE: u32 = 1024;
+
+// every `factor` x `factor` block of the image becomes one preview pixel, 1 means no preview is needed
+pub fn preview_factor(width: u32, height: u32) -> u32 {
+    width.max(height).div_ceil(PREVIEW_SIZE).max(1)
+}
+
+pub struct PreviewBase {
+    pub factor: u32,
+    pub grayscale: GrayImage,
+    // the overlay of the preview is drawn on top of this
+    pub image: RgbaImage,
+}
+
+impl PreviewBase {
+    pub fn new(image: &DynamicImage, factor: u32) -> Self {
+        let (width, height) = (
+            (image.width() / factor).max(1),
+            (image.height() / factor).max(1),
+        );
+        // no full size copy, large images take several gigabytes as RGBA
+        let image = imageops::thumbnail(image, width, height);
+
+        Self {
+            factor,
+            grayscale: DynamicImage::ImageRgba8(image.clone()).to_luma8(),
+            image,
+        }
+    }
+
+    // the mean grey value of every block, read strip by strip so the image is never decoded as a whole
+    pub fn from_strips(source: &mut impl StripSource, factor: u32) -> Result<Self, String> {
+        let (image_width, image_height) = source.dimensions();
+        let (width, height) = (
+            (image_width / factor).max(1),
+            (image_height / factor).max(1),
+        );
+        // sum of the grey values and number of pixels of every block
+        let mut sums = vec![(0u64, 0u64); width as usize * height as usize];
+
+        let mut strip_y = 0;
+        for index in 0..source.strip_count() {
+            let strip = source.read_strip(index)?;
+            for (x, y, p) in strip.enumerate_pixels() {
+                let (x, y) = (x / factor, (strip_y + y) / factor);
+                if x < width && y < height {
+                    let sum = &mut sums[(y * width + x) as usize];
+                    sum.0 += p[0] as u64;
+                    sum.1 += 1;
+                }
+            }
+            strip_y += strip.height();
+        }
+
+        let grayscale = GrayImage::from_fn(width, height, |x, y| {
+            let (sum, count) = sums[(y * width + x) as usize];
+            Luma([(sum / count.max(1)) as u8])
+        });
+
+        Ok(Self {
+            factor,
+            image: DynamicImage::ImageLuma8(grayscale.clone()).to_rgba8(),
+            grayscale,
+        })
+    }
+}
+
+// sizes are areas and shrink with the square of the factor
+fn scale_parameters(parameters: &AnalysisParameters, factor: u32) -> AnalysisParameters {
+    let (factor, area_factor) = (factor as f64, (factor * factor) as f32);
+    let scale_point = |point: PlotPoint| PlotPoint::new(point.x / factor, point.y / factor);
+
+    AnalysisParameters {
+        threshold: parameters.threshold,
+        minimal_pore_size_low: parameters.minimal_pore_size_low / area_factor,
+        minimal_pore_size_high: parameters.minimal_pore_size_high / area_factor,
+        included_min_feature_size: parameters.included_min_feature_size / area_factor,
+        region_start: parameters.region_start.map(scale_point),
+        region_end: parameters.region_end.map(scale_point),
+    }
+}
+
+pub fn analyze_preview(
+    base: &PreviewBase,
+    parameters: &AnalysisParameters,
+    manual_overrides: &ManualOverrides,
+    excluded_pores: &[ExcludedPore],
+    control: &JobControl,
+) -> Option<AnalysisResult> {
+    let factor = base.factor;
+    let (width, height) = base.grayscale.dimensions();
+
+    let manual_overrides = manual_overrides
+        .iter()
+        .map(|(&(x, y), value)| ((x / factor, y / factor), *value))
+        .filter(|((x, y), _)| *x < width && *y < height)
+        .collect::<ManualOverrides>();
+    let excluded_pores = excluded_pores
+        .iter()
+        .map(|excluded_pore| ExcludedPore {
+            centroid: PlotPoint::new(
+                excluded_pore.centroid.x / factor as f64,
+                excluded_pore.centroid.y / factor as f64,
+            ),
+            area: excluded_pore.area / (factor * factor) as usize,
+        })
+        .collect::<Vec<_>>();
+
+    analyze_grayscale(
+        &base.grayscale,
+        &scale_parameters(parameters, factor),
+        &manual_overrides,
+        &excluded_pores,
+        control,
+    )
+}
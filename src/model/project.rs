@@ -0,0 +1,516 @@
+use std::path::{Path, PathBuf};
+
+use egui_plot::PlotPoint;
+use serde::{Deserialize, Serialize};
+
+use crate::view::{
+    depth_profile_window::DepthProfileSettings, display_settings_window::OverlaySettings,
+    export_window::ExportDecimalFormat, histogram::HistogramSettings, plot::BrushSettings,
+    plot::PlotTool,
+};
+
+use super::{
+    autosave::project_state,
+    depth_profile::ReferenceLine,
+    detection_app::PoreDetectionApp,
+    excluded_pores::ExcludedPore,
+    ground_truth::load_ground_truth,
+    image_data::ImageData,
+    manual_mask::{insert_override_run, override_runs, ManualOverride},
+    relink::{file_fingerprint, relative_path, resolve_path, PendingProject},
+};
+
+// version of the project files written by this program, it has to be increased whenever
+// a change can't be read by the old version, older files are then converted in `load_project`
+pub const PROJECT_VERSION: u32 = 2;
+
+pub const PROJECT_EXTENSION: &str = "pdproj";
+
+// plot points are stored as `[x, y]`
+pub mod plot_point {
+    use egui_plot::PlotPoint;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(point: &PlotPoint, serializer: S) -> Result<S::Ok, S::Error> {
+        [point.x, point.y].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PlotPoint, D::Error> {
+        let [x, y] = <[f64; 2]>::deserialize(deserializer)?;
+        Ok(PlotPoint::new(x, y))
+    }
+}
+
+// only the version, to find out how the rest of the file has to be read
+#[derive(Deserialize)]
+struct ProjectHeader {
+    version: u32,
+}
+
+// everything needed to continue working on a project, the results are analyzed again after loading
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Project {
+    pub version: u32,
+    pub images: Vec<ProjectImage>,
+    pub selected: Option<usize>,
+    pub settings: ProjectSettings,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self {
+            version: PROJECT_VERSION,
+            images: Vec::new(),
+            selected: None,
+            settings: ProjectSettings::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectImage {
+    // relative to the project file if they share a folder
+    pub path: PathBuf,
+    // to find the image again if it was renamed, see `file_fingerprint`
+    pub fingerprint: Option<u64>,
+    pub threshold: i16,
+    pub minimal_pore_size_low: f32,
+    pub minimal_pore_size_high: f32,
+    pub included_min_feature_size: f32,
+    pub region_start: Option<[f64; 2]>,
+    pub region_end: Option<[f64; 2]>,
+    pub reference_line: Option<ReferenceLine>,
+    pub ground_truth_path: Option<PathBuf>,
+    // runs of pixels in one column (x, y start, y end, value)
+    pub manual_overrides: Vec<(u32, u32, u32, ManualOverride)>,
+    pub excluded_pores: Vec<ExcludedPore>,
+    // shown until the image is analyzed again
+    pub density: Option<f64>,
+}
+
+impl Default for ProjectImage {
+    fn default() -> Self {
+        Self::from_image(&ImageData::default())
+    }
+}
+
+// there is no calibration yet, all sizes are in pixels
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ProjectSettings {
+    pub plot_tool: PlotTool,
+    pub brush: BrushSettings,
+    pub histogram: HistogramSettings,
+    pub overlay: OverlaySettings,
+    pub depth_profile: DepthProfileSettings,
+    pub ground_truth_overlay: bool,
+    pub export_decimal_format: ExportDecimalFormat,
+    pub sweep_range: Option<(i16, i16)>,
+    pub sweep_uncertainty_levels: Option<i16>,
+    pub batch_workers: Option<usize>,
+}
+
+impl ProjectImage {
+    pub fn from_image(image: &ImageData) -> Self {
+        let to_array = |point: PlotPoint| [point.x, point.y];
+
+        Self {
+            path: image.path.clone().unwrap_or_default(),
+            fingerprint: None,
+            threshold: image.threshold,
+            minimal_pore_size_low: image.minimal_pore_size_low,
+            minimal_pore_size_high: image.minimal_pore_size_high,
+            included_min_feature_size: image.included_min_feature_size,
+            region_start: image.region_start.map(to_array),
+            region_end: image.region_end.map(to_array),
+            reference_line: image.reference_line,
+            ground_truth_path: image.ground_truth_path.clone(),
+            manual_overrides: override_runs(&image.manual_overrides),
+            excluded_pores: image.excluded_pores.clone(),
+            density: image.density,
+        }
+    }
+
+    // the analysis parameters, the image itself and the results stay
+    pub fn apply_parameters(&self, image: &mut ImageData) {
+        let to_point = |[x, y]: [f64; 2]| PlotPoint::new(x, y);
+
+        image.threshold = self.threshold;
+        image.minimal_pore_size_low = self.minimal_pore_size_low;
+        image.minimal_pore_size_high = self.minimal_pore_size_high;
+        image.included_min_feature_size = self.included_min_feature_size;
+        image.region_start = self.region_start.map(to_point);
+        image.region_end = self.region_end.map(to_point);
+        image.excluded_pores = self.excluded_pores.clone();
+    }
+
+    // a ground truth that can't be used is dropped and reported in `problems`
+    pub fn into_image_data(
+        self,
+        ctx: &egui::Context,
+        problems: &mut Vec<String>,
+    ) -> Result<ImageData, String> {
+        let image_data = ImageData::open(ctx, self.path.clone())?;
+        let ground_truth = match (&self.ground_truth_path, image_data.dimensions) {
+            (Some(path), Some(dimensions)) => load_ground_truth(path, dimensions)
+                .inspect_err(|err| {
+                    let problem = format!(
+                        "{:?}: the ground truth {:?} was removed, {}",
+                        self.path, path, err
+                    );
+                    log::warn!("{}", problem);
+                    problems.push(problem);
+                })
+                .ok(),
+            _ => None,
+        };
+        let to_point = |[x, y]: [f64; 2]| PlotPoint::new(x, y);
+
+        let mut image_data = ImageData {
+            threshold: self.threshold,
+            minimal_pore_size_low: self.minimal_pore_size_low,
+            minimal_pore_size_high: self.minimal_pore_size_high,
+            included_min_feature_size: self.included_min_feature_size,
+            region_start: self.region_start.map(to_point),
+            region_end: self.region_end.map(to_point),
+            reference_line: self.reference_line,
+            ground_truth_path: self.ground_truth_path.filter(|_| ground_truth.is_some()),
+            ground_truth,
+            excluded_pores: self.excluded_pores,
+            density: self.density,
+            ..image_data
+        };
+        if let Some(dimensions) = image_data.dimensions {
+            let mut skipped = 0;
+            for run in self.manual_overrides {
+                if let Err(err) =
+                    insert_override_run(&mut image_data.manual_overrides, run, dimensions)
+                {
+                    log::warn!("Skipped manual edit of {:?}: {}", self.path, err);
+                    skipped += 1;
+                }
+            }
+            if skipped > 0 {
+                problems.push(format!(
+                    "{:?}: {} manual edits are outside of the image or invalid and were skipped",
+                    self.path, skipped
+                ));
+            }
+        }
+
+        Ok(image_data)
+    }
+}
+
+impl Project {
+    fn map_paths(&mut self, map: impl Fn(&Path) -> PathBuf) {
+        for image in &mut self.images {
+            image.path = map(&image.path);
+            image.ground_truth_path = image.ground_truth_path.as_deref().map(&map);
+        }
+    }
+
+    pub fn from_app(app: &PoreDetectionApp) -> Self {
+        Self {
+            version: PROJECT_VERSION,
+            images: app
+                .images
+                .images
+                .iter()
+                .map(ProjectImage::from_image)
+                .collect(),
+            selected: app.images.selected,
+            settings: ProjectSettings {
+                plot_tool: app.plot_tool,
+                brush: app.brush.clone(),
+                histogram: app.histogram_settings.clone(),
+                overlay: app.overlay_settings.clone(),
+                depth_profile: app.depth_profile_settings.clone(),
+                ground_truth_overlay: app.ground_truth_overlay,
+                export_decimal_format: app.export_decimal_format.clone(),
+                sweep_range: Some((
+                    app.threshold_sweep.range_start,
+                    app.threshold_sweep.range_end,
+                )),
+                sweep_uncertainty_levels: Some(app.threshold_sweep.uncertainty_levels),
+                batch_workers: Some(app.batch_settings.workers),
+            },
+        }
+    }
+
+    // fails if one of the images can't be opened
+    pub fn into_app(self, ctx: &egui::Context) -> Result<PoreDetectionApp, String> {
+        let mut app = PoreDetectionApp::default();
+
+        for image in self.images {
+            let image = image.into_image_data(ctx, &mut app.import_problems)?;
+            app.images.images.push(image);
+        }
+
+        let settings = self.settings;
+        app.plot_tool = settings.plot_tool;
+        app.brush = settings.brush;
+        app.histogram_settings = settings.histogram;
+        app.overlay_settings = settings.overlay;
+        app.depth_profile_settings = settings.depth_profile;
+        app.ground_truth_overlay = settings.ground_truth_overlay;
+        app.export_decimal_format = settings.export_decimal_format;
+        if let Some((range_start, range_end)) = settings.sweep_range {
+            app.threshold_sweep.range_start = range_start;
+            app.threshold_sweep.range_end = range_end;
+        }
+        if let Some(uncertainty_levels) = settings.sweep_uncertainty_levels {
+            app.threshold_sweep.uncertainty_levels = uncertainty_levels;
+        }
+        if let Some(workers) = settings.batch_workers {
+            app.batch_settings.workers = workers;
+        }
+
+        if !app.images.images.is_empty() {
+            let selected = self
+                .selected
+                .filter(|selected| *selected < app.images.images.len())
+                .unwrap_or(0);
+            app.reload_image(Some(selected));
+            app.image_to_display = app.images.images[selected].image_handle.clone();
+        }
+
+        Ok(app)
+    }
+}
+
+// the folder of the project file, paths are stored relative to it
+fn project_folder(path: &Path) -> PathBuf {
+    std::path::absolute(path)
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .unwrap_or_default()
+}
+
+pub fn save_project(app: &PoreDetectionApp, path: &Path) -> Result<(), String> {
+    let folder = project_folder(path);
+    let mut project = Project::from_app(app);
+    // only computed here, it reads the start of every image
+    for image in &mut project.images {
+        image.fingerprint = file_fingerprint(&image.path);
+    }
+    project.map_paths(|image_path| relative_path(&folder, image_path));
+
+    let project = ron::ser::to_string_pretty(&project, ron::ser::PrettyConfig::default())
+        .map_err(|err| format!("Could not write the project: {}", err))?;
+
+    std::fs::write(path, project).map_err(|err| format!("Could not save {:?}: {}", path, err))
+}
+
+pub fn load_project(path: &Path) -> Result<Project, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not open {:?}: {}", path, err))?;
+    let header: ProjectHeader =
+        ron::from_str(&text).map_err(|err| format!("{:?} is not a project file: {}", path, err))?;
+
+    // files of older versions are converted step by step to the current version here
+    let mut project: Project = match header.version {
+        PROJECT_VERSION => {
+            ron::from_str(&text).map_err(|err| format!("Could not read {:?}: {}", path, err))?
+        }
+        // version 1 only had absolute paths and no fingerprints, both are handled by the defaults
+        1 => Project {
+            version: PROJECT_VERSION,
+            ..ron::from_str(&text).map_err(|err| format!("Could not read {:?}: {}", path, err))?
+        },
+        version if version > PROJECT_VERSION => Err(format!(
+            "The project was saved with a newer version of this program (project version {}, supported up to {}).",
+            version, PROJECT_VERSION
+        ))?,
+        version => Err(format!("Unknown project version {}", version))?,
+    };
+
+    let folder = project_folder(path);
+    project.map_paths(|image_path| resolve_path(&folder, image_path));
+
+    Ok(project)
+}
+
+// shows the error in a dialog, the project files are only used on native
+pub fn show_project_error(err: &str) {
+    log::error!("{}", err);
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title("Project")
+        .set_description(err)
+        .show();
+}
+
+// `Save` writes to the file the project was opened from, `Save As` or a new project asks for a file
+pub fn save_project_dialog(app: &mut PoreDetectionApp, save_as: bool) {
+    let path = match (&app.project_path, save_as) {
+        (Some(path), false) => Some(path.clone()),
+        _ => rfd::FileDialog::new()
+            .add_filter("Project", &[PROJECT_EXTENSION])
+            .set_file_name(format!("project.{}", PROJECT_EXTENSION))
+            .save_file(),
+    };
+    let Some(path) = path else {
+        return;
+    };
+
+    match save_project(app, &path) {
+        Ok(()) => {
+            log::info!("Saved project to {:?}", path);
+            app.project_path = Some(path);
+            app.saved_state = Some(project_state(app).1);
+        }
+        Err(err) => show_project_error(&err),
+    }
+}
+
+pub fn open_project_dialog(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("Project", &[PROJECT_EXTENSION])
+        .pick_file()
+    else {
+        return;
+    };
+    log::info!("Opening project {:?}", path);
+
+    match load_project(&path) {
+        Ok(project) => open_project(ctx, app, PendingProject::new(project, Some(path))),
+        Err(err) => show_project_error(&err),
+    }
+}
+
+// opens the project right away if all images are there, otherwise they have to be relinked first
+pub fn open_project(ctx: &egui::Context, app: &mut PoreDetectionApp, pending: PendingProject) {
+    if pending.missing().is_empty() {
+        finish_opening_project(ctx, app, pending);
+    } else {
+        log::warn!(
+            "{} images of the project are missing",
+            pending.missing().len()
+        );
+        app.pending_project = Some(pending);
+    }
+}
+
+pub fn finish_opening_project(
+    ctx: &egui::Context,
+    app: &mut PoreDetectionApp,
+    pending: PendingProject,
+) {
+    match pending.project.into_app(ctx) {
+        Ok(new_app) => {
+            *app = new_app;
+            app.project_path = pending.path;
+            app.import_problems.splice(0..0, pending.problems);
+            if pending.saved {
+                app.saved_state = Some(project_state(app).1);
+            }
+        }
+        Err(err) => show_project_error(&err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{load_project, Project, ProjectImage, PROJECT_VERSION};
+    use crate::model::manual_mask::ManualOverride;
+
+    // writes the project text into a folder of its own, so relative paths have a known base
+    fn load_text(name: &str, text: &str) -> (PathBuf, Result<Project, String>) {
+        let folder = std::env::temp_dir().join(format!(
+            "pore_detection_project_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("project.pdp");
+        std::fs::write(&path, text).unwrap();
+
+        let project = load_project(&path);
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        (std::path::absolute(folder).unwrap(), project)
+    }
+
+    #[test]
+    fn version_1_projects_are_converted() {
+        // version 1 had absolute paths, no fingerprints and fewer settings
+        let (_, project) = load_text(
+            "v1",
+            r#"(
+                version: 1,
+                images: [(
+                    path: "/data/specimen.png",
+                    threshold: 120,
+                    minimal_pore_size_low: 4.0,
+                    region_start: Some((10.0, 90.0)),
+                    region_end: Some((60.0, 20.0)),
+                    manual_overrides: [(3, 1, 4, Pore)],
+                )],
+                selected: Some(0),
+            )"#,
+        );
+        let project = project.unwrap();
+
+        assert_eq!(project.version, PROJECT_VERSION);
+        assert_eq!(project.selected, Some(0));
+        assert_eq!(project.images.len(), 1);
+        let image = &project.images[0];
+        assert_eq!(image.path, Path::new("/data/specimen.png"));
+        assert_eq!(image.fingerprint, None);
+        assert_eq!(image.threshold, 120);
+        assert_eq!(image.minimal_pore_size_low, 4.0);
+        // missing fields get the defaults of a new image
+        assert_eq!(
+            image.minimal_pore_size_high,
+            ProjectImage::default().minimal_pore_size_high
+        );
+        assert_eq!(image.region_start, Some([10.0, 90.0]));
+        assert_eq!(image.region_end, Some([60.0, 20.0]));
+        assert_eq!(
+            image.manual_overrides,
+            vec![(3, 1, 4, ManualOverride::Pore)]
+        );
+    }
+
+    #[test]
+    fn relative_paths_are_resolved_against_the_project_folder() {
+        let (folder, project) = load_text(
+            "relative",
+            r#"(
+                version: 2,
+                images: [
+                    (path: "images/a.png", ground_truth_path: Some("../masks/a.png")),
+                    (path: "/data/b.png"),
+                ],
+            )"#,
+        );
+        let project = project.unwrap();
+
+        assert_eq!(project.images[0].path, folder.join("images/a.png"));
+        assert_eq!(
+            project.images[0].ground_truth_path,
+            Some(folder.parent().unwrap().join("masks/a.png"))
+        );
+        assert_eq!(project.images[1].path, Path::new("/data/b.png"));
+    }
+
+    #[test]
+    fn newer_and_unknown_versions_are_rejected() {
+        let (_, newer) = load_text(
+            "newer",
+            &format!("(version: {}, images: [])", PROJECT_VERSION + 1),
+        );
+        assert!(newer.err().unwrap().contains("newer version"));
+
+        let (_, unknown) = load_text("unknown", "(version: 0, images: [])");
+        assert!(unknown.err().unwrap().contains("Unknown project version"));
+
+        let (_, invalid) = load_text("invalid", "not a project");
+        assert!(invalid.err().unwrap().contains("is not a project file"));
+    }
+}
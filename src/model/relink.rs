@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use super::project::Project;
+
+// only the start of a file is hashed, that is enough to tell images apart and fast for large ones
+const FINGERPRINT_BYTES: u64 = 1024 * 1024;
+
+const IMAGE_EXTENSIONS: [&str; 9] = [
+    "png", "jpg", "jpeg", "tif", "tiff", "bmp", "tga", "exr", "avif",
+];
+
+// `path` relative to the directory `base`, as long as they share more than the root
+// the components are joined with `/`, so the project can be opened on every platform
+pub fn relative_path(base: &Path, path: &Path) -> PathBuf {
+    if !base.is_absolute() || !path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let base_components = base.components().collect::<Vec<_>>();
+    let path_components = path.components().collect::<Vec<_>>();
+    let roots = path_components
+        .iter()
+        .take_while(|component| matches!(component, Component::Prefix(_) | Component::RootDir))
+        .count();
+    let common = base_components
+        .iter()
+        .zip(&path_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common <= roots {
+        return path.to_path_buf();
+    }
+
+    let relative = std::iter::repeat_n("..", base_components.len() - common)
+        .map(str::to_string)
+        .chain(
+            path_components[common..]
+                .iter()
+                .map(|component| component.as_os_str().to_string_lossy().to_string()),
+        )
+        .collect::<Vec<_>>();
+
+    PathBuf::from(relative.join("/"))
+}
+
+// the inverse of `relative_path`, `..` is removed so the paths look like the ones picked in a dialog
+pub fn resolve_path(base: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let mut resolved = base.to_path_buf();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            component => resolved.push(component),
+        }
+    }
+
+    resolved
+}
+
+// FNV-1a of the file size and the first megabyte
+pub fn file_fingerprint(path: &Path) -> Option<u64> {
+    let file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+
+    let mut bytes = Vec::new();
+    file.take(FINGERPRINT_BYTES).read_to_end(&mut bytes).ok()?;
+
+    let fingerprint = size
+        .to_le_bytes()
+        .iter()
+        .chain(&bytes)
+        .fold(0xcbf29ce484222325, |hash: u64, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        });
+
+    Some(fingerprint)
+}
+
+fn find_images(folder: &Path, images: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_images(&path, images);
+        } else if path.extension().is_some_and(|extension| {
+            IMAGE_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())
+        }) {
+            images.push(path);
+        }
+    }
+}
+
+// a project whose images could not all be found, it is opened once they are relinked
+pub struct PendingProject {
+    pub project: Project,
+    // the project file, `None` for imported projects
+    pub path: Option<PathBuf>,
+    // compare the file contents when searching a folder, this also finds renamed images
+    pub match_contents: bool,
+    // the project is the same as its file, restored sessions and imports are not
+    pub saved: bool,
+    // shown once the project is open, e.g. rows of an Excel import that were skipped
+    pub problems: Vec<String>,
+}
+
+impl PendingProject {
+    pub fn new(project: Project, path: Option<PathBuf>) -> Self {
+        Self {
+            saved: path.is_some(),
+            project,
+            path,
+            match_contents: false,
+            problems: Vec::new(),
+        }
+    }
+
+    pub fn missing(&self) -> Vec<usize> {
+        (0..self.project.images.len())
+            .filter(|i| !self.project.images[*i].path.is_file())
+            .collect()
+    }
+
+    // opening without the missing images, the selection is kept if its image is there
+    pub fn remove_missing(&mut self) {
+        let missing = self.missing();
+        let project = &mut self.project;
+
+        project.selected = project
+            .selected
+            .filter(|selected| !missing.contains(selected))
+            .map(|selected| selected - missing.iter().filter(|i| **i < selected).count());
+        let mut i = 0;
+        project.images.retain(|_| {
+            i += 1;
+            !missing.contains(&(i - 1))
+        });
+    }
+
+    // searches the folder and its subfolders for all missing images, returns how many were found
+    pub fn relink_in_folder(&mut self, folder: &Path) -> usize {
+        let mut images = Vec::new();
+        find_images(folder, &mut images);
+
+        let mut by_name: HashMap<_, Vec<&PathBuf>> = HashMap::new();
+        for image in &images {
+            if let Some(name) = image.file_name() {
+                by_name.entry(name.to_os_string()).or_default().push(image);
+            }
+        }
+        // the fingerprints are only computed once and only when needed
+        let mut fingerprints = HashMap::new();
+        let mut fingerprint = |path: &PathBuf| {
+            *fingerprints
+                .entry(path.clone())
+                .or_insert_with(|| file_fingerprint(path))
+        };
+
+        let mut found = 0;
+        for i in self.missing() {
+            let image = &self.project.images[i];
+            let candidates = image
+                .path
+                .file_name()
+                .and_then(|name| by_name.get(name))
+                .cloned()
+                .unwrap_or_default();
+
+            let relinked = match (self.match_contents, image.fingerprint) {
+                (true, Some(expected)) => candidates
+                    .into_iter()
+                    .chain(&images)
+                    .find(|candidate| fingerprint(candidate) == Some(expected)),
+                _ => candidates.into_iter().next(),
+            };
+
+            if let Some(relinked) = relinked {
+                log::info!("Relinked {:?} to {:?}", image.path, relinked);
+                self.project.images[i].path = relinked.clone();
+                found += 1;
+            }
+        }
+
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{relative_path, resolve_path, PendingProject};
+    use crate::model::project::{Project, ProjectImage};
+
+    #[test]
+    fn relative_paths_across_directories() {
+        let base = Path::new("/home/user/projects/specimen");
+        let cases = [
+            ("/home/user/projects/specimen/a.png", "a.png"),
+            ("/home/user/projects/specimen/images/a.png", "images/a.png"),
+            ("/home/user/projects/other/a.png", "../other/a.png"),
+            ("/home/user/data/scans/a.png", "../../data/scans/a.png"),
+            // only the root is shared
+            ("/mnt/scans/a.png", "/mnt/scans/a.png"),
+        ];
+
+        for (path, expected) in cases {
+            let relative = relative_path(base, Path::new(path));
+            assert_eq!(relative, Path::new(expected), "{}", path);
+            assert_eq!(resolve_path(base, &relative), Path::new(path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn relative_paths_stay_unchanged() {
+        let base = Path::new("/home/user/projects");
+        assert_eq!(
+            relative_path(Path::new("projects"), Path::new("/data/a.png")),
+            Path::new("/data/a.png")
+        );
+        assert_eq!(
+            relative_path(base, Path::new("images/a.png")),
+            Path::new("images/a.png")
+        );
+        assert_eq!(
+            resolve_path(base, Path::new("./images/../a.png")),
+            Path::new("/home/user/projects/a.png")
+        );
+    }
+
+    fn pending_project(paths: &[PathBuf], selected: Option<usize>) -> PendingProject {
+        let images = paths
+            .iter()
+            .map(|path| ProjectImage {
+                path: path.clone(),
+                ..Default::default()
+            })
+            .collect();
+        PendingProject::new(
+            Project {
+                images,
+                selected,
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn missing_images_are_removed_and_the_selection_is_kept() {
+        let folder = std::env::temp_dir().join(format!(
+            "pore_detection_relink_remove_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&folder).unwrap();
+        let existing = folder.join("existing.png");
+        std::fs::write(&existing, b"image").unwrap();
+
+        let mut pending = pending_project(
+            &[
+                folder.join("missing_1.png"),
+                existing.clone(),
+                folder.join("missing_2.png"),
+            ],
+            Some(1),
+        );
+        assert_eq!(pending.missing(), vec![0, 2]);
+        pending.remove_missing();
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        assert_eq!(pending.project.images.len(), 1);
+        assert_eq!(pending.project.images[0].path, existing);
+        assert_eq!(pending.project.selected, Some(0));
+    }
+
+    #[test]
+    fn images_are_relinked_by_name_and_by_content() {
+        let folder = std::env::temp_dir().join(format!(
+            "pore_detection_relink_folder_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(folder.join("moved")).unwrap();
+        std::fs::write(folder.join("moved/a.png"), b"first image").unwrap();
+        std::fs::write(folder.join("moved/renamed.png"), b"second image").unwrap();
+        // both images were moved, the second one was also renamed
+        let old_folder = folder.join("old");
+        let renamed = folder.join("moved/renamed.png");
+
+        let mut pending =
+            pending_project(&[old_folder.join("a.png"), old_folder.join("b.png")], None);
+        assert_eq!(pending.relink_in_folder(&folder), 1);
+        assert_eq!(pending.project.images[0].path, folder.join("moved/a.png"));
+        assert_eq!(pending.missing(), vec![1]);
+
+        // a renamed image is only found by the fingerprint of its contents
+        pending.project.images[1].fingerprint = super::file_fingerprint(&renamed);
+        pending.match_contents = true;
+        assert_eq!(pending.relink_in_folder(&folder), 1);
+        assert_eq!(pending.project.images[1].path, renamed);
+        assert!(pending.missing().is_empty());
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
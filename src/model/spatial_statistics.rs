@@ -0,0 +1,221 @@
+use egui_plot::PlotPoint;
+
+const RIPLEY_STEPS: usize = 20;
+
+#[derive(Clone, Debug)]
+pub struct RipleyPoint {
+    pub radius: f64,
+    pub k: f64,
+    pub l: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct SpatialStatistics {
+    pub pore_count: usize,
+    pub nearest_neighbour_distances: Vec<f64>,
+    pub mean_nearest_neighbour_distance: f64,
+    pub std_nearest_neighbour_distance: f64,
+    // side length of the square each pore would occupy if they were spread evenly
+    pub mean_spacing: f64,
+    // < 1 clustered, ~1 random, > 1 regular
+    pub clark_evans_index: f64,
+    // size of the analyzed area in pixels and the largest radius for Ripley's K
+    pub area: f64,
+    pub max_ripley_radius: f64,
+    // only computed when it is shown or exported, see `ImageData::ripley`
+    pub ripley: Option<Vec<RipleyPoint>>,
+}
+
+// `area` is the size of the analyzed area in pixels, `max_radius` the largest radius for Ripley's K
+pub fn compute_spatial_statistics(
+    centroids: &[PlotPoint],
+    area: f64,
+    max_radius: f64,
+) -> Option<SpatialStatistics> {
+    let n = centroids.len();
+    if n < 2 || area <= 0.0 || max_radius <= 0.0 {
+        return None;
+    }
+
+    // sort by x so only the points in a small x window have to be compared
+    let mut points = centroids.to_vec();
+    points.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+    let mut nearest_neighbour_distances = Vec::with_capacity(n);
+    for (i, point) in points.iter().enumerate() {
+        let mut best = f64::INFINITY;
+
+        for other in points[i + 1..].iter() {
+            if other.x - point.x >= best {
+                break;
+            }
+            best = best.min(distance(point, other));
+        }
+        for other in points[..i].iter().rev() {
+            if point.x - other.x >= best {
+                break;
+            }
+            best = best.min(distance(point, other));
+        }
+
+        nearest_neighbour_distances.push(best);
+    }
+
+    let mean_nearest_neighbour_distance =
+        nearest_neighbour_distances.iter().sum::<f64>() / n as f64;
+    let std_nearest_neighbour_distance = (nearest_neighbour_distances
+        .iter()
+        .map(|d| (d - mean_nearest_neighbour_distance).powi(2))
+        .sum::<f64>()
+        / n as f64)
+        .sqrt();
+
+    // expected mean nearest neighbour distance of a random (poisson) distribution
+    let expected_distance = 0.5 / (n as f64 / area).sqrt();
+
+    Some(SpatialStatistics {
+        pore_count: n,
+        nearest_neighbour_distances,
+        mean_nearest_neighbour_distance,
+        std_nearest_neighbour_distance,
+        mean_spacing: (area / n as f64).sqrt(),
+        clark_evans_index: mean_nearest_neighbour_distance / expected_distance,
+        area,
+        max_ripley_radius: max_radius,
+        ripley: None,
+    })
+}
+
+fn distance(a: &PlotPoint, b: &PlotPoint) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+// ripley's K and L function, a large part of all pairs is within `max_radius`, so this is too slow
+// to run with every analysis of an image with many pores
+pub fn compute_ripley(centroids: &[PlotPoint], area: f64, max_radius: f64) -> Vec<RipleyPoint> {
+    let n = centroids.len();
+    if n < 2 || max_radius <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut points = centroids.to_vec();
+    points.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+    // count the pairs for each radius step, every pair is counted once
+    let step = max_radius / RIPLEY_STEPS as f64;
+    let mut pair_counts = [0usize; RIPLEY_STEPS];
+    for (i, point) in points.iter().enumerate() {
+        for other in points[i + 1..].iter() {
+            if other.x - point.x > max_radius {
+                break;
+            }
+
+            let d = distance(point, other);
+            if d <= max_radius {
+                let bin = ((d / step).ceil() as usize).clamp(1, RIPLEY_STEPS) - 1;
+                pair_counts[bin] += 1;
+            }
+        }
+    }
+
+    let mut cumulative_pairs = 0;
+    pair_counts
+        .iter()
+        .enumerate()
+        .map(|(i, count)| {
+            cumulative_pairs += count;
+            let k = area * (2 * cumulative_pairs) as f64 / (n * (n - 1)) as f64;
+
+            RipleyPoint {
+                radius: (i + 1) as f64 * step,
+                k,
+                l: (k / std::f64::consts::PI).sqrt(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the corners of a 10 px square in an area of 400 px²
+    fn square() -> Vec<PlotPoint> {
+        vec![
+            PlotPoint::new(0.0, 0.0),
+            PlotPoint::new(10.0, 0.0),
+            PlotPoint::new(0.0, 10.0),
+            PlotPoint::new(10.0, 10.0),
+        ]
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn nearest_neighbours_and_clark_evans_index_of_a_square() {
+        let statistics = compute_spatial_statistics(&square(), 400.0, 20.0).unwrap();
+
+        assert_eq!(statistics.pore_count, 4);
+        assert_eq!(statistics.nearest_neighbour_distances, vec![10.0; 4]);
+        assert_close(statistics.mean_nearest_neighbour_distance, 10.0);
+        assert_close(statistics.std_nearest_neighbour_distance, 0.0);
+        assert_close(statistics.mean_spacing, 10.0);
+        // a random distribution of 4 points in 400 px² has a mean distance of 0.5 / sqrt(4 / 400) = 5
+        assert_close(statistics.clark_evans_index, 2.0);
+        assert!(statistics.ripley.is_none());
+    }
+
+    #[test]
+    fn nearest_neighbour_is_found_on_both_sides() {
+        let points = [
+            PlotPoint::new(0.0, 0.0),
+            PlotPoint::new(3.0, 4.0),
+            PlotPoint::new(20.0, 0.0),
+        ];
+        let statistics = compute_spatial_statistics(&points, 400.0, 10.0).unwrap();
+
+        // sorted by x
+        assert_eq!(
+            statistics.nearest_neighbour_distances,
+            vec![5.0, 5.0, (17.0f64.powi(2) + 16.0).sqrt()]
+        );
+    }
+
+    #[test]
+    fn too_few_points_have_no_statistics() {
+        assert!(compute_spatial_statistics(&square()[..1], 400.0, 20.0).is_none());
+        assert!(compute_spatial_statistics(&square(), 0.0, 20.0).is_none());
+        assert!(compute_ripley(&square()[..1], 400.0, 20.0).is_empty());
+    }
+
+    #[test]
+    fn ripley_k_of_a_square() {
+        let ripley = compute_ripley(&square(), 400.0, 20.0);
+        assert_eq!(ripley.len(), RIPLEY_STEPS);
+
+        // 4 pairs are 10 px apart and the 2 diagonals 14.14 px
+        let k = |radius: f64| {
+            ripley
+                .iter()
+                .find(|point| point.radius == radius)
+                .unwrap()
+                .k
+        };
+        assert_close(k(9.0), 0.0);
+        assert_close(k(10.0), 400.0 * 8.0 / 12.0);
+        assert_close(k(14.0), 400.0 * 8.0 / 12.0);
+        assert_close(k(15.0), 400.0);
+        assert_close(k(20.0), 400.0);
+
+        for point in &ripley {
+            assert_close(point.l, (point.k / std::f64::consts::PI).sqrt());
+        }
+    }
+}
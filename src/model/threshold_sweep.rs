@@ -0,0 +1,135 @@
+use std::{
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use super::{
+    image_data::{analyze_grayscale, AnalysisParameters, ImageData},
+    job::JobControl,
+    tiled::{analyze_tiled, LargeImageStrips},
+};
+
+#[derive(Clone, Debug)]
+pub struct ThresholdSweepPoint {
+    pub threshold: i16,
+    pub density: f64,
+    pub pore_count: usize,
+}
+
+#[derive(Clone)]
+pub struct ThresholdSweep {
+    // parameters of the image when the sweep was started, only the threshold is varied
+    pub parameters: AnalysisParameters,
+    pub points: Vec<ThresholdSweepPoint>,
+}
+
+impl ThresholdSweep {
+    pub fn density_at(&self, threshold: i16) -> Option<f64> {
+        self.points
+            .iter()
+            .find(|point| point.threshold == threshold)
+            .map(|point| point.density)
+    }
+
+    // the sweep is only valid as long as every parameter except the threshold is unchanged
+    pub fn matches(&self, parameters: &AnalysisParameters) -> bool {
+        AnalysisParameters {
+            threshold: parameters.threshold,
+            ..self.parameters
+        } == *parameters
+    }
+
+    // largest change of the density when the threshold is moved by `uncertainty_levels` grey levels,
+    // thresholds without material pixels have no density (NaN) and are left out
+    pub fn uncertainty(&self, threshold: i16, uncertainty_levels: i16) -> Option<f64> {
+        let density = self
+            .density_at(threshold)
+            .filter(|density| density.is_finite())?;
+
+        [
+            threshold - uncertainty_levels,
+            threshold + uncertainty_levels,
+        ]
+        .iter()
+        .filter_map(|threshold| self.density_at(*threshold))
+        .filter(|other| other.is_finite())
+        .map(|other| (other - density).abs())
+        .reduce(f64::max)
+    }
+}
+
+// analyzes the image like `ImageData::analyze_image` for every threshold, large images are streamed
+// strip by strip for each of them, `None` if the sweep was cancelled or the image could not be read
+pub fn run_threshold_sweep(
+    image_data: &ImageData,
+    thresholds: RangeInclusive<i16>,
+    progress: Arc<AtomicUsize>,
+    control: Arc<JobControl>,
+) -> JoinHandle<Option<ThresholdSweep>> {
+    let image = image_data.image.clone();
+    let path = image_data.path.clone();
+    let is_large = image_data.is_large();
+    // only read, the cache of the image may be cleared while the sweep runs
+    let cache = image_data.pipeline_cache.clone();
+    let parameters = image_data.analysis_parameters();
+    let manual_overrides = image_data.manual_overrides.clone();
+    let excluded_pores = image_data.excluded_pores.clone();
+
+    progress.store(0, Ordering::Relaxed);
+
+    std::thread::spawn(move || {
+        let mut large_strips = if is_large {
+            let strips = LargeImageStrips::open(image.as_deref(), path.as_deref())
+                .inspect_err(|err| log::warn!("Threshold sweep failed: {}", err))
+                .ok()?;
+            Some(strips)
+        } else {
+            None
+        };
+        let grayscale = match (&large_strips, cache.grayscale()) {
+            (Some(_), _) => None,
+            (None, Some(grayscale)) => Some(grayscale),
+            (None, None) => Some(Arc::new(image.as_ref()?.grayscale().to_luma8())),
+        };
+
+        let mut points = Vec::new();
+        for threshold in thresholds {
+            let parameters = AnalysisParameters {
+                threshold,
+                ..parameters
+            };
+            let result = if let Some(strips) = &mut large_strips {
+                analyze_tiled(
+                    strips,
+                    &parameters,
+                    &manual_overrides,
+                    &excluded_pores,
+                    &control,
+                )
+                .inspect_err(|err| log::warn!("Threshold sweep failed: {}", err))
+                .ok()??
+            } else {
+                analyze_grayscale(
+                    grayscale.as_deref()?,
+                    &parameters,
+                    &manual_overrides,
+                    &excluded_pores,
+                    &control,
+                )?
+            };
+            progress.fetch_add(1, Ordering::Relaxed);
+
+            points.push(ThresholdSweepPoint {
+                threshold,
+                density: result.density,
+                pore_count: result.pores.len(),
+            });
+        }
+
+        Some(ThresholdSweep { parameters, points })
+    })
+}
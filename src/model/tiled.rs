@@ -0,0 +1,765 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use egui_plot::PlotPoint;
+use image::{imageops, DynamicImage, GrayImage, ImageBuffer, Luma};
+use imageproc::{
+    definitions::{HasBlack, HasWhite},
+    region_labelling::{connected_components, Connectivity},
+};
+use tiff::{
+    decoder::{ChunkType, Decoder, DecodingResult},
+    tags::Tag,
+    ColorType,
+};
+
+use super::{
+    excluded_pores::ExcludedPore,
+    image_data::{AnalysisParameters, AnalysisResult, LabelImage, Pore, PoreSize},
+    job::JobControl,
+    manual_mask::{apply_manual_overrides, ManualOverrides},
+    pixel_mask::PixelMask,
+};
+
+// larger images are analyzed strip by strip instead of being labelled as a whole, TIFF images are streamed from disk
+pub const TILED_ANALYSIS_PIXELS: u64 = 8192 * 8192;
+// strip height of large images that are already in memory
+pub const STRIP_HEIGHT: u32 = 256;
+
+pub fn is_large_image(width: u32, height: u32) -> bool {
+    width as u64 * height as u64 > TILED_ANALYSIS_PIXELS
+}
+
+// an image read as horizontal grayscale strips from top to bottom, every strip is read once per pass
+pub trait StripSource {
+    fn dimensions(&self) -> (u32, u32);
+    fn strip_count(&self) -> u32;
+    fn read_strip(&mut self, index: u32) -> Result<GrayImage, String>;
+}
+
+// strips of an image that is already in memory
+pub struct GrayStrips<'a> {
+    image: &'a GrayImage,
+    strip_height: u32,
+}
+
+impl<'a> GrayStrips<'a> {
+    pub fn new(image: &'a GrayImage, strip_height: u32) -> Self {
+        Self {
+            image,
+            strip_height: strip_height.max(1),
+        }
+    }
+}
+
+impl StripSource for GrayStrips<'_> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.image.dimensions()
+    }
+
+    fn strip_count(&self) -> u32 {
+        self.image.height().div_ceil(self.strip_height)
+    }
+
+    fn read_strip(&mut self, index: u32) -> Result<GrayImage, String> {
+        let (width, height) = self.image.dimensions();
+        let y = index * self.strip_height;
+
+        Ok(
+            imageops::crop_imm(self.image, 0, y, width, self.strip_height.min(height - y))
+                .to_image(),
+        )
+    }
+}
+
+// grayscale strips of an image in memory, only one strip is converted at a time
+pub struct ImageStrips<'a> {
+    image: &'a DynamicImage,
+    strip_height: u32,
+}
+
+impl<'a> ImageStrips<'a> {
+    pub fn new(image: &'a DynamicImage, strip_height: u32) -> Self {
+        Self {
+            image,
+            strip_height: strip_height.max(1),
+        }
+    }
+}
+
+impl StripSource for ImageStrips<'_> {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.image.width(), self.image.height())
+    }
+
+    fn strip_count(&self) -> u32 {
+        self.image.height().div_ceil(self.strip_height)
+    }
+
+    // the conversion works per pixel, so the strips are the same as the strips of the converted image
+    fn read_strip(&mut self, index: u32) -> Result<GrayImage, String> {
+        let (width, height) = self.dimensions();
+        let y = index * self.strip_height;
+
+        Ok(self
+            .image
+            .crop_imm(0, y, width, self.strip_height.min(height - y))
+            .grayscale()
+            .to_luma8())
+    }
+}
+
+// strips (or rows of tiles) of a TIFF file, only one of them is decoded at a time
+pub struct TiffStrips {
+    decoder: Decoder<BufReader<File>>,
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    chunk_type: ChunkType,
+    chunk_width: u32,
+    chunk_height: u32,
+}
+
+impl TiffStrips {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|err| format!("could not open image: {}", err))?;
+        let mut decoder = Decoder::new(BufReader::new(file))
+            .map_err(|err| format!("could not read TIFF: {}", err))?;
+
+        let (width, height) = decoder.dimensions().map_err(|err| err.to_string())?;
+        let color_type = decoder.colortype().map_err(|err| err.to_string())?;
+        if !matches!(
+            color_type,
+            ColorType::Gray(8 | 16)
+                | ColorType::GrayA(8 | 16)
+                | ColorType::RGB(8 | 16)
+                | ColorType::RGBA(8 | 16)
+        ) {
+            return Err(format!("unsupported TIFF colour type {:?}", color_type));
+        }
+
+        // separate colour planes and inverted grey values are left to the image decoder
+        let planar_configuration = decoder
+            .find_tag_unsigned::<u16>(Tag::PlanarConfiguration)
+            .map_err(|err| err.to_string())?;
+        let photometric_interpretation = decoder
+            .find_tag_unsigned::<u16>(Tag::PhotometricInterpretation)
+            .map_err(|err| err.to_string())?;
+        if planar_configuration == Some(2) || photometric_interpretation == Some(0) {
+            return Err("unsupported TIFF layout".to_string());
+        }
+
+        let (chunk_width, chunk_height) = decoder.chunk_dimensions();
+
+        Ok(Self {
+            chunk_type: decoder.get_chunk_type(),
+            decoder,
+            width,
+            height,
+            color_type,
+            chunk_width,
+            chunk_height,
+        })
+    }
+
+    // converts the decoded samples the same way as `image::open(..).grayscale()`
+    fn to_grayscale(&self, width: u32, height: u32, data: DecodingResult) -> Option<GrayImage> {
+        let image = match (self.color_type, data) {
+            (ColorType::Gray(8), DecodingResult::U8(data)) => {
+                DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, data)?)
+            }
+            (ColorType::Gray(16), DecodingResult::U16(data)) => {
+                DynamicImage::ImageLuma16(ImageBuffer::from_raw(width, height, data)?)
+            }
+            (ColorType::GrayA(8), DecodingResult::U8(data)) => {
+                DynamicImage::ImageLumaA8(ImageBuffer::from_raw(width, height, data)?)
+            }
+            (ColorType::GrayA(16), DecodingResult::U16(data)) => {
+                DynamicImage::ImageLumaA16(ImageBuffer::from_raw(width, height, data)?)
+            }
+            (ColorType::RGB(8), DecodingResult::U8(data)) => {
+                DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, data)?)
+            }
+            (ColorType::RGB(16), DecodingResult::U16(data)) => {
+                DynamicImage::ImageRgb16(ImageBuffer::from_raw(width, height, data)?)
+            }
+            (ColorType::RGBA(8), DecodingResult::U8(data)) => {
+                DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, data)?)
+            }
+            (ColorType::RGBA(16), DecodingResult::U16(data)) => {
+                DynamicImage::ImageRgba16(ImageBuffer::from_raw(width, height, data)?)
+            }
+            _ => return None,
+        };
+
+        Some(image.grayscale().to_luma8())
+    }
+
+    fn read_chunk(&mut self, index: u32) -> Result<GrayImage, String> {
+        let (width, height) = self.decoder.chunk_data_dimensions(index);
+        let data = self
+            .decoder
+            .read_chunk(index)
+            .map_err(|err| format!("could not read TIFF chunk {}: {}", index, err))?;
+
+        self.to_grayscale(width, height, data)
+            .ok_or_else(|| format!("unexpected data in TIFF chunk {}", index))
+    }
+}
+
+impl StripSource for TiffStrips {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn strip_count(&self) -> u32 {
+        self.height.div_ceil(self.chunk_height)
+    }
+
+    fn read_strip(&mut self, index: u32) -> Result<GrayImage, String> {
+        match self.chunk_type {
+            ChunkType::Strip => self.read_chunk(index),
+            ChunkType::Tile => {
+                let tiles_across = self.width.div_ceil(self.chunk_width);
+                let mut strip = GrayImage::new(
+                    self.width,
+                    self.chunk_height
+                        .min(self.height - index * self.chunk_height),
+                );
+
+                for column in 0..tiles_across {
+                    let tile = self.read_chunk(index * tiles_across + column)?;
+                    imageops::replace(&mut strip, &tile, (column * self.chunk_width) as i64, 0);
+                }
+
+                Ok(strip)
+            }
+        }
+    }
+}
+
+// only TIFF images are streamed, `None` for everything that fits the usual analysis
+pub fn open_large_tiff(path: &Path) -> Option<TiffStrips> {
+    let is_tiff = path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("tif") || extension.eq_ignore_ascii_case("tiff")
+    });
+    if !is_tiff {
+        return None;
+    }
+
+    let strips = TiffStrips::open(path)
+        .inspect_err(|err| log::warn!("Can not stream {:?}: {}", path, err))
+        .ok()?;
+    let (width, height) = strips.dimensions();
+
+    is_large_image(width, height).then_some(strips)
+}
+
+// the strips of a large image, streamed from disk for TIFF files and converted from the decoded image otherwise
+pub enum LargeImageStrips<'a> {
+    Tiff(Box<TiffStrips>),
+    Image(ImageStrips<'a>),
+}
+
+impl<'a> LargeImageStrips<'a> {
+    pub fn open(image: Option<&'a DynamicImage>, path: Option<&Path>) -> Result<Self, String> {
+        match (path.and_then(open_large_tiff), image) {
+            (Some(tiff_strips), _) => Ok(Self::Tiff(Box::new(tiff_strips))),
+            (None, Some(image)) => Ok(Self::Image(ImageStrips::new(image, STRIP_HEIGHT))),
+            (None, None) => Err("the image is not loaded".to_string()),
+        }
+    }
+}
+
+impl StripSource for LargeImageStrips<'_> {
+    fn dimensions(&self) -> (u32, u32) {
+        match self {
+            Self::Tiff(strips) => strips.dimensions(),
+            Self::Image(strips) => strips.dimensions(),
+        }
+    }
+
+    fn strip_count(&self) -> u32 {
+        match self {
+            Self::Tiff(strips) => strips.strip_count(),
+            Self::Image(strips) => strips.strip_count(),
+        }
+    }
+
+    fn read_strip(&mut self, index: u32) -> Result<GrayImage, String> {
+        match self {
+            Self::Tiff(strips) => strips.read_strip(index),
+            Self::Image(strips) => strips.read_strip(index),
+        }
+    }
+}
+
+// union-find over the labels of all strips, label `l` of strip `i` has the id `offsets[i] + l`
+struct Components {
+    parent: Vec<u32>,
+    size: Vec<usize>,
+    offsets: Vec<u32>,
+}
+
+impl Components {
+    fn new() -> Self {
+        // id 0 is the background
+        Self {
+            parent: vec![0],
+            size: vec![0],
+            offsets: Vec::new(),
+        }
+    }
+
+    fn add_strip(&mut self, labels: &LabelImage) -> u32 {
+        let offset = self.parent.len() as u32 - 1;
+        let num_labels = *labels.iter().max().unwrap_or(&0);
+
+        self.parent.extend(offset + 1..=offset + num_labels);
+        self.size.resize(self.parent.len(), 0);
+        self.offsets.push(offset);
+
+        offset
+    }
+
+    fn find(&mut self, mut id: u32) -> u32 {
+        while self.parent[id as usize] != id {
+            self.parent[id as usize] = self.parent[self.parent[id as usize] as usize];
+            id = self.parent[id as usize];
+        }
+        id
+    }
+
+    fn union(&mut self, a: u32, b: u32) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[a.max(b) as usize] = a.min(b);
+        }
+    }
+
+    // joins the labels of the first row of a strip with the 8 neighbours in the last row of the strip above
+    fn join_seam(&mut self, previous_row: &[u32], labels: &LabelImage, offset: u32) {
+        let width = labels.width();
+        for x in 0..width {
+            let label = labels.get_pixel(x, 0)[0];
+            if label == 0 {
+                continue;
+            }
+
+            for neighbour_x in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                let neighbour = previous_row[neighbour_x as usize];
+                if neighbour != 0 {
+                    self.union(offset + label, neighbour);
+                }
+            }
+        }
+    }
+}
+
+// labels the strip the same way as `label_grayscale` labels the whole image
+fn label_strip(strip: &GrayImage, threshold: i16) -> (LabelImage, LabelImage) {
+    let strip_thresh = imageproc::contrast::threshold(
+        strip,
+        threshold.try_into().unwrap(),
+        imageproc::contrast::ThresholdType::Binary,
+    );
+
+    (
+        connected_components(&strip_thresh, Connectivity::Eight, Luma::white()),
+        connected_components(&strip_thresh, Connectivity::Eight, Luma::black()),
+    )
+}
+
+// same result as `analyze_grayscale` on the whole image, but only one strip of labels is in memory at a time
+// the image is read twice, first to join the labels across the strip seams and then to build the masks
+// `Ok(None)` if the analysis was cancelled
+pub fn analyze_tiled(
+    source: &mut impl StripSource,
+    parameters: &AnalysisParameters,
+    manual_overrides: &ManualOverrides,
+    excluded_pores: &[ExcludedPore],
+    control: &JobControl,
+) -> Result<Option<AnalysisResult>, String> {
+    let AnalysisParameters {
+        threshold,
+        included_min_feature_size,
+        ..
+    } = *parameters;
+
+    let (width, height) = source.dimensions();
+    let region = parameters.image_region(height);
+    let is_in_region = |x: u32, y: u32| match region {
+        Some((x_start, x_end, y_start, y_end)) => {
+            x >= x_start && x <= x_end && y >= y_start && y <= y_end
+        }
+        None => true,
+    };
+
+    // groups of black pixels (pore candidates) and of white pixels (material)
+    let mut pore_components = Components::new();
+    let mut material_components = Components::new();
+    // raster index of the first pixel and the sum of x, sum of y and number of pixels inside the region per id
+    let mut first_pixel = vec![u64::MAX];
+    let mut region_sums = vec![(0u64, 0u64, 0usize)];
+    let (mut white_count, mut black_count) = (0, 0);
+
+    let mut previous_rows: Option<(Vec<u32>, Vec<u32>)> = None;
+    let mut strip_y = 0;
+    for index in 0..source.strip_count() {
+        if control.is_cancelled() {
+            return Ok(None);
+        }
+
+        let strip = source.read_strip(index)?;
+        let (labels, black_labels) = label_strip(&strip, threshold);
+
+        let pore_offset = pore_components.add_strip(&labels);
+        let material_offset = material_components.add_strip(&black_labels);
+        first_pixel.resize(pore_components.parent.len(), u64::MAX);
+        region_sums.resize(pore_components.parent.len(), (0, 0, 0));
+
+        labels.enumerate_pixels().for_each(|(x, y, p)| {
+            if p[0] == 0 {
+                white_count += 1;
+                return;
+            }
+
+            let (id, y) = ((pore_offset + p[0]) as usize, strip_y + y);
+            pore_components.size[id] += 1;
+            first_pixel[id] = first_pixel[id].min(y as u64 * width as u64 + x as u64);
+            if is_in_region(x, y) {
+                let sums = &mut region_sums[id];
+                sums.0 += x as u64;
+                sums.1 += y as u64;
+                sums.2 += 1;
+            }
+        });
+        black_labels.pixels().for_each(|p| match p[0] {
+            0 => black_count += 1,
+            label => material_components.size[(material_offset + label) as usize] += 1,
+        });
+
+        if let Some((previous_pore_row, previous_material_row)) = &previous_rows {
+            pore_components.join_seam(previous_pore_row, &labels, pore_offset);
+            material_components.join_seam(previous_material_row, &black_labels, material_offset);
+        }
+
+        let last_row = |labels: &LabelImage, offset: u32| {
+            (0..width)
+                .map(|x| match labels.get_pixel(x, labels.height() - 1)[0] {
+                    0 => 0,
+                    label => offset + label,
+                })
+                .collect::<Vec<_>>()
+        };
+        previous_rows = Some((
+            last_row(&labels, pore_offset),
+            last_row(&black_labels, material_offset),
+        ));
+        strip_y += strip.height();
+    }
+
+    if strip_y != height {
+        return Err(format!("expected {} rows but read {}", height, strip_y));
+    }
+
+    // sum up every group at its root
+    for id in 1..pore_components.parent.len() as u32 {
+        let root = pore_components.find(id);
+        if root != id {
+            let (id, root) = (id as usize, root as usize);
+            pore_components.size[root] += pore_components.size[id];
+            first_pixel[root] = first_pixel[root].min(first_pixel[id]);
+            let sums = region_sums[id];
+            region_sums[root].0 += sums.0;
+            region_sums[root].1 += sums.1;
+            region_sums[root].2 += sums.2;
+        }
+    }
+    for id in 1..material_components.parent.len() as u32 {
+        let root = material_components.find(id) as usize;
+        if root != id as usize {
+            material_components.size[root] += material_components.size[id as usize];
+        }
+    }
+
+    // `connected_components` numbers the groups in the order of their first pixel
+    let mut roots = (1..pore_components.parent.len() as u32)
+        .filter(|id| pore_components.parent[*id as usize] == *id)
+        .collect::<Vec<_>>();
+    roots.sort_unstable_by_key(|root| first_pixel[*root as usize]);
+
+    let mut root_labels = vec![0u32; pore_components.parent.len()];
+    let mut label_sizes = vec![white_count];
+    let mut label_sums = vec![(0, 0, 0)];
+    for (i, root) in roots.iter().enumerate() {
+        root_labels[*root as usize] = i as u32 + 1;
+        label_sizes.push(pore_components.size[*root as usize]);
+        label_sums.push(region_sums[*root as usize]);
+    }
+
+    let is_pore_size = |size: usize| parameters.pore_size(size) == PoreSize::Passed;
+    let (excluded, pores): (Vec<_>, Vec<_>) = label_sums
+        .iter()
+        .enumerate()
+        .filter(|(label, (_, _, count))| *count > 0 && is_pore_size(label_sizes[*label]))
+        .map(|(label, (sum_x, sum_y, count))| Pore {
+            label: label as u32,
+            area: *count,
+            centroid: PlotPoint::new(*sum_x as f64 / *count as f64, *sum_y as f64 / *count as f64),
+        })
+        .partition(|pore| {
+            excluded_pores
+                .iter()
+                .any(|excluded_pore| excluded_pore.matches(pore))
+        });
+    let mut is_excluded = vec![false; label_sizes.len()];
+    excluded
+        .iter()
+        .for_each(|pore| is_excluded[pore.label as usize] = true);
+
+    // second pass, the strips are labelled exactly like in the first one
+    let mut green_pixels = PixelMask::new(width, height);
+    let mut white_pixels = PixelMask::new(width, height);
+    let mut strip_y = 0;
+    for index in 0..source.strip_count() {
+        if control.is_cancelled() {
+            return Ok(None);
+        }
+
+        let strip = source.read_strip(index)?;
+        let (labels, black_labels) = label_strip(&strip, threshold);
+        let (pore_offset, material_offset) = (
+            pore_components.offsets[index as usize],
+            material_components.offsets[index as usize],
+        );
+
+        for (x, y, p) in labels.enumerate_pixels() {
+            let y_image = strip_y + y;
+            if !is_in_region(x, y_image) {
+                continue;
+            }
+
+            // small groups of white pixels (e.g. corrosion or scratches inside a pore) count as pore
+            let is_filled = included_min_feature_size > 0.0 && {
+                let material_size = match black_labels.get_pixel(x, y)[0] {
+                    0 => black_count,
+                    label => {
+                        let root = material_components.find(material_offset + label);
+                        material_components.size[root as usize]
+                    }
+                };
+                (material_size as i32) < included_min_feature_size as i32
+            };
+
+            if p[0] != 0 {
+                let root = pore_components.find(pore_offset + p[0]);
+                let label = root_labels[root as usize] as usize;
+                // excluded pores are treated like pores outside of the size range
+                if (is_pore_size(label_sizes[label]) || is_filled) && !is_excluded[label] {
+                    green_pixels.set(x, y_image, true);
+                }
+            } else if is_filled {
+                green_pixels.set(x, y_image, true);
+            } else {
+                white_pixels.set(x, y_image, true);
+            }
+        }
+
+        strip_y += strip.height();
+    }
+    log::info!("marked black pixels: {:?}", green_pixels.count());
+
+    // manual edits always win over the automatic segmentation
+    apply_manual_overrides(
+        &mut green_pixels,
+        &mut white_pixels,
+        manual_overrides,
+        region,
+    );
+
+    // calculate the density for the whole image
+    let density = (1.0 - (green_pixels.count() as f64 / white_pixels.count() as f64)) * 100.0;
+
+    Ok(Some(AnalysisResult {
+        green_pixels,
+        white_pixels,
+        density,
+        pores,
+        excluded_pores: excluded,
+        labels: None,
+        label_sizes: label_sizes.into(),
+        spatial_statistics: None,
+        histogram: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_plot::PlotPoint;
+    use image::{GrayImage, Luma};
+
+    use super::{analyze_tiled, GrayStrips, ImageStrips, TiffStrips};
+    use crate::model::{
+        excluded_pores::ExcludedPore,
+        image_data::{analyze_grayscale, AnalysisParameters, AnalysisResult},
+        job::JobControl,
+    };
+
+    // bright material with dark round pores, some of them with small bright spots inside
+    fn synthetic_image(size: u32) -> GrayImage {
+        let mut seed: u64 = 0x5eed;
+        let mut random = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as u32
+        };
+
+        let mut image = GrayImage::from_fn(size, size, |_, _| Luma([180 + (random() % 60) as u8]));
+
+        for _ in 0..(size * size / 400) {
+            let (center_x, center_y) = (random() % size, random() % size);
+            let radius = 1 + random() % 8;
+
+            for y in center_y.saturating_sub(radius)..(center_y + radius).min(size) {
+                for x in center_x.saturating_sub(radius)..(center_x + radius).min(size) {
+                    let distance = (x as f64 - center_x as f64).hypot(y as f64 - center_y as f64);
+                    if distance <= radius as f64 {
+                        let value = if radius > 4 && distance < 1.0 {
+                            200
+                        } else {
+                            (random() % 60) as u8
+                        };
+                        image.put_pixel(x, y, Luma([value]));
+                    }
+                }
+            }
+        }
+
+        image
+    }
+
+    fn assert_same_result(expected: &AnalysisResult, result: &AnalysisResult) {
+        assert!(
+            expected.green_pixels == result.green_pixels,
+            "pore pixels differ"
+        );
+        assert!(
+            expected.white_pixels == result.white_pixels,
+            "material pixels differ"
+        );
+        assert_eq!(expected.density, result.density, "density differs");
+        assert_eq!(
+            expected.label_sizes, result.label_sizes,
+            "label sizes differ"
+        );
+
+        for (expected, result) in [
+            (&expected.pores, &result.pores),
+            (&expected.excluded_pores, &result.excluded_pores),
+        ] {
+            assert_eq!(expected.len(), result.len(), "number of pores differs");
+            for (expected, pore) in expected.iter().zip(result) {
+                assert_eq!(
+                    (expected.label, expected.area, expected.centroid),
+                    (pore.label, pore.area, pore.centroid),
+                    "pores differ"
+                );
+            }
+        }
+    }
+
+    // the whole image analysis with a region and a few excluded pores
+    fn expected_result(
+        image: &GrayImage,
+    ) -> (AnalysisParameters, Vec<ExcludedPore>, AnalysisResult) {
+        let (width, height) = image.dimensions();
+        let parameters = AnalysisParameters {
+            threshold: 100,
+            minimal_pore_size_low: 3.0,
+            minimal_pore_size_high: 150.0,
+            included_min_feature_size: 20.0,
+            region_start: Some(PlotPoint::new(width as f64 * 0.1, height as f64 * 0.8)),
+            region_end: Some(PlotPoint::new(width as f64 * 0.9, height as f64 * 0.2)),
+        };
+        let control = JobControl::default();
+
+        let result =
+            analyze_grayscale(image, &parameters, &Default::default(), &[], &control).unwrap();
+        // exclude a few pores to check that they are matched the same way
+        let excluded_pores = result
+            .pores
+            .iter()
+            .step_by(7)
+            .map(ExcludedPore::from_pore)
+            .collect::<Vec<_>>();
+        let result = analyze_grayscale(
+            image,
+            &parameters,
+            &Default::default(),
+            &excluded_pores,
+            &control,
+        )
+        .unwrap();
+
+        (parameters, excluded_pores, result)
+    }
+
+    #[test]
+    fn tiled_analysis_matches_whole_image_for_any_strip_height() {
+        let image = synthetic_image(256);
+        let (parameters, excluded_pores, expected) = expected_result(&image);
+
+        for strip_height in [1, 7, 64, image.height()] {
+            let result = analyze_tiled(
+                &mut GrayStrips::new(&image, strip_height),
+                &parameters,
+                &Default::default(),
+                &excluded_pores,
+                &JobControl::default(),
+            )
+            .unwrap()
+            .unwrap();
+            assert_same_result(&expected, &result);
+        }
+    }
+
+    #[test]
+    fn tiled_analysis_of_decoded_image_matches_whole_image() {
+        let image = synthetic_image(256);
+        let (parameters, excluded_pores, expected) = expected_result(&image);
+
+        let result = analyze_tiled(
+            &mut ImageStrips::new(&image::DynamicImage::ImageLuma8(image), 50),
+            &parameters,
+            &Default::default(),
+            &excluded_pores,
+            &JobControl::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_same_result(&expected, &result);
+    }
+
+    #[test]
+    fn tiled_analysis_streamed_from_tiff_matches_whole_image() {
+        let image = synthetic_image(256);
+        let (parameters, excluded_pores, expected) = expected_result(&image);
+
+        let path = std::env::temp_dir().join(format!(
+            "pore_detection_tiled_test_{}.tif",
+            std::process::id()
+        ));
+        image.save(&path).unwrap();
+        let result = analyze_tiled(
+            &mut TiffStrips::open(&path).unwrap(),
+            &parameters,
+            &Default::default(),
+            &excluded_pores,
+            &JobControl::default(),
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        assert_same_result(&expected, &result.unwrap().unwrap());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeSet;
+
+use egui::{DragValue, Grid, ProgressBar, ScrollArea};
+
+use crate::{
+    model::{
+        batch::{BatchQueue, BatchState},
+        csv::apply_parameters_csv,
+        project::show_project_error,
+    },
+    PoreDetectionApp,
+};
+
+pub struct BatchSettings {
+    pub workers: usize,
+    // image ids for `Analyze selected`
+    pub selection: BTreeSet<u64>,
+}
+
+impl Default for BatchSettings {
+    fn default() -> Self {
+        // leave one core for the ui
+        let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+
+        Self {
+            workers: cores.saturating_sub(1).max(1),
+            selection: BTreeSet::new(),
+        }
+    }
+}
+
+pub fn display_batch_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    let mut window_open = app.batch_window_open;
+
+    egui::Window::new("Batch Analysis")
+        .open(&mut window_open)
+        .show(ctx, |ui| {
+            if app.images.images.is_empty() {
+                ui.label("Load images to analyze them.");
+                return;
+            }
+
+            let is_running = app.batch.as_ref().is_some_and(|batch| !batch.is_finished());
+
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(!is_running, |ui| {
+                    if ui.button("Analyze all").clicked() {
+                        let image_ids = app
+                            .images
+                            .images
+                            .iter()
+                            .map(|image| image.id)
+                            .collect::<Vec<_>>();
+                        app.batch = Some(BatchQueue::start(
+                            &app.images.images,
+                            &image_ids,
+                            app.batch_settings.workers,
+                        ));
+                    }
+
+                    if ui
+                        .add_enabled(
+                            !app.batch_settings.selection.is_empty(),
+                            egui::Button::new("Analyze selected"),
+                        )
+                        .clicked()
+                    {
+                        let image_ids = app
+                            .batch_settings
+                            .selection
+                            .iter()
+                            .copied()
+                            .collect::<Vec<_>>();
+                        app.batch = Some(BatchQueue::start(
+                            &app.images.images,
+                            &image_ids,
+                            app.batch_settings.workers,
+                        ));
+                    }
+
+                    ui.add(
+                        DragValue::new(&mut app.batch_settings.workers)
+                            .range(1..=64)
+                            .prefix("Workers: "),
+                    );
+                });
+
+                if ui
+                    .button("Apply Parameters (CSV)")
+                    .on_hover_text("Set the parameters of the loaded images from an exported CSV or TSV file and analyze them again")
+                    .clicked()
+                {
+                    let path = rfd::FileDialog::new()
+                        .add_filter("CSV", &["csv", "tsv", "txt"])
+                        .pick_file();
+                    if let Some(path) = path {
+                        match apply_parameters_csv(app, &path) {
+                            Ok(problems) => app.import_problems = problems,
+                            Err(err) => show_project_error(&err),
+                        }
+                    }
+                }
+            });
+
+            if let Some(batch) = &app.batch {
+                let total = batch.entries.len();
+                let done = batch.count(|state| {
+                    matches!(
+                        state,
+                        BatchState::Done(_) | BatchState::Failed(_) | BatchState::Cancelled
+                    )
+                });
+                let failed = batch.count(|state| matches!(state, BatchState::Failed(_)));
+
+                ui.add(
+                    ProgressBar::new(done as f32 / total.max(1) as f32)
+                        .text(format!("{} / {}", done, total)),
+                );
+
+                ui.horizontal(|ui| {
+                    if let (false, Some(eta)) = (batch.is_finished(), batch.eta()) {
+                        ui.label(format!("ETA: {:.0}s", eta.as_secs_f64()));
+                    }
+                    if failed > 0 {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("{} failed", failed));
+                    }
+
+                    if !batch.is_finished() {
+                        let paused = batch.control.is_paused();
+                        // the workers only check it between images
+                        let text = if paused {
+                            "Resume"
+                        } else {
+                            "Pause after current images"
+                        };
+                        if ui.button(text).clicked() {
+                            batch.set_paused(!paused);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            log::info!("Cancel batch analysis");
+                            batch.cancel();
+                        }
+                    }
+                });
+            }
+
+            ui.separator();
+
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                Grid::new("batch_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for image in &app.images.images {
+                            let filename = image
+                                .path
+                                .as_ref()
+                                .and_then(|path| path.file_name())
+                                .map_or("-".to_string(), |name| name.to_string_lossy().to_string());
+
+                            let mut selected = app.batch_settings.selection.contains(&image.id);
+                            if ui.checkbox(&mut selected, filename).changed() {
+                                if selected {
+                                    app.batch_settings.selection.insert(image.id);
+                                } else {
+                                    app.batch_settings.selection.remove(&image.id);
+                                }
+                            }
+
+                            let state = app
+                                .batch
+                                .as_ref()
+                                .and_then(|batch| batch.state(image.id).cloned());
+                            match state {
+                                Some(BatchState::Queued) => {
+                                    ui.label("Queued");
+                                }
+                                Some(BatchState::Running(start)) => {
+                                    ui.horizontal(|ui| {
+                                        ui.spinner();
+                                        ui.label(format!("{:.1}s", start.elapsed().as_secs_f64()));
+                                    });
+                                }
+                                Some(BatchState::Done(duration)) => {
+                                    ui.label(format!("Done ({:.1}s)", duration.as_secs_f64()));
+                                }
+                                Some(BatchState::Failed(err)) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, err);
+                                }
+                                Some(BatchState::Cancelled) => {
+                                    ui.label("Cancelled");
+                                }
+                                None => {
+                                    ui.label("");
+                                }
+                            }
+
+                            match image.density {
+                                Some(density) => ui.label(format!("{:.5}%", density)),
+                                None => ui.label("-"),
+                            };
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+
+    app.batch_window_open = window_open;
+}
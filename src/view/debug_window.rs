@@ -14,7 +14,10 @@ pub fn display_debug_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
 
             if ui.button("Show grayscale").clicked() {
                 let selected_img = app.images.selected.unwrap_or(0);
-                let image = app.images.images[selected_img].image.clone().unwrap();
+                // large TIFF images are not in memory
+                let Some(image) = app.images.images[selected_img].image.clone() else {
+                    return;
+                };
                 let grayscale = image.grayscale().to_luma8();
                 let grayscale_dynamic = image::DynamicImage::ImageLuma8(grayscale);
 
@@ -24,7 +27,9 @@ pub fn display_debug_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
 
             if ui.button("Show thresholded").clicked() {
                 let selected_img = app.images.selected.unwrap_or(0);
-                let image = app.images.images[selected_img].image.clone().unwrap();
+                let Some(image) = app.images.images[selected_img].image.clone() else {
+                    return;
+                };
                 let grayscale = image.grayscale().to_luma8();
                 let grayscale_thresh = imageproc::contrast::threshold(
                     &grayscale,
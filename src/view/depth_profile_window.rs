@@ -0,0 +1,134 @@
+use egui::{ComboBox, DragValue};
+use egui_plot::{Bar, BarChart, Legend, MarkerShape, Plot, Points};
+
+use crate::{model::depth_profile::ReferenceLine, view::plot::PlotTool, PoreDetectionApp};
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DepthProfileSettings {
+    pub band_width: f64,
+}
+
+impl Default for DepthProfileSettings {
+    fn default() -> Self {
+        Self { band_width: 50.0 }
+    }
+}
+
+pub fn display_depth_profile_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    let mut window_open = app.depth_profile_window_open;
+
+    egui::Window::new("Depth Profile")
+        .open(&mut window_open)
+        .show(ctx, |ui| {
+            let Some(selected_img) = app.images.selected else {
+                ui.label("Load an image to compute a depth profile.");
+                return;
+            };
+
+            let band_width = app.depth_profile_settings.band_width;
+            let current_image = &mut app.images.images[selected_img];
+            let mut reference_line = current_image.reference_line;
+
+            ui.horizontal(|ui| {
+                ui.label("Reference:");
+                ComboBox::from_id_salt("depth_profile_reference")
+                    .selected_text(match reference_line {
+                        Some(reference_line) => format!("{}", reference_line),
+                        None => "None".to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut reference_line, None, "None");
+                        for edge in [
+                            ReferenceLine::TopEdge,
+                            ReferenceLine::BottomEdge,
+                            ReferenceLine::LeftEdge,
+                            ReferenceLine::RightEdge,
+                        ] {
+                            ui.selectable_value(
+                                &mut reference_line,
+                                Some(edge),
+                                format!("{}", edge),
+                            );
+                        }
+                    });
+
+                if ui
+                    .selectable_label(app.plot_tool == PlotTool::ReferenceLine, "Draw line")
+                    .on_hover_text("Drag with the right mouse button on the image")
+                    .clicked()
+                {
+                    app.plot_tool = if app.plot_tool == PlotTool::ReferenceLine {
+                        PlotTool::Region
+                    } else {
+                        PlotTool::ReferenceLine
+                    };
+                }
+            });
+
+            let mut new_band_width = band_width;
+            ui.horizontal(|ui| {
+                ui.label("Band width:");
+                ui.add(
+                    DragValue::new(&mut new_band_width)
+                        .range(1.0..=10000.0)
+                        .suffix(" px"),
+                );
+            });
+
+            if reference_line != current_image.reference_line || new_band_width != band_width {
+                app.depth_profile_settings.band_width = new_band_width;
+                current_image.reference_line = reference_line;
+                current_image.update_depth_profile(new_band_width);
+            }
+
+            ui.separator();
+
+            if let Some(depth_profile) = &current_image.depth_profile {
+                let bars = depth_profile
+                    .iter()
+                    .filter_map(|band| {
+                        band.porosity().map(|porosity| {
+                            Bar::new((band.start + band.end) / 2.0, porosity)
+                                .width(band.end - band.start)
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                // bands without any analyzed pixel, e.g. outside of the region
+                let no_data = depth_profile
+                    .iter()
+                    .filter(|band| band.porosity().is_none())
+                    .map(|band| [(band.start + band.end) / 2.0, 0.0])
+                    .collect::<Vec<_>>();
+
+                Plot::new("depth_profile_plot")
+                    .height(250.0)
+                    .allow_scroll(false)
+                    .x_axis_label("Distance from reference [px] (negative left of a drawn line)")
+                    .y_axis_label("Porosity [%]")
+                    .include_y(0.0)
+                    .legend(Legend::default())
+                    .show(ui, |plot_ui| {
+                        plot_ui.bar_chart(BarChart::new(bars).name("Porosity"));
+                        if !no_data.is_empty() {
+                            plot_ui.points(
+                                Points::new(no_data)
+                                    .shape(MarkerShape::Cross)
+                                    .radius(4.0)
+                                    .name("No data"),
+                            );
+                        }
+                    });
+
+                if ui.button("Export Profile").clicked() {
+                    app.images.export_depth_profile(selected_img);
+                }
+            } else if current_image.reference_line.is_some() {
+                ui.label("The profile is computed as soon as the analysis is finished.");
+            } else {
+                ui.label("Choose an image edge or draw a reference line.");
+            }
+        });
+
+    app.depth_profile_window_open = window_open;
+}
@@ -0,0 +1,204 @@
+use egui::{Color32, Slider};
+
+use crate::PoreDetectionApp;
+
+// viridis like colour map for the colour by size mode, from small to large pores
+const SIZE_COLOR_MAP: [[u8; 3]; 5] = [
+    [68, 1, 84],
+    [59, 82, 139],
+    [33, 145, 140],
+    [94, 201, 98],
+    [253, 231, 37],
+];
+
+const FILLED_FEATURE_COLOR: [u8; 3] = [255, 165, 0];
+const MANUAL_PORE_COLOR: [u8; 3] = [255, 0, 255];
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Deserialize, serde::Serialize)]
+pub enum PoreColoring {
+    #[default]
+    Uniform,
+    BySize,
+    ByClass,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PoreClass {
+    Detected,
+    // small material islands that were filled because of the minimal feature size
+    FilledFeature,
+    Manual,
+}
+
+// only changes how the overlay is drawn, never the analysis
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct OverlaySettings {
+    pub pore_color: [u8; 4],
+    pub material_color: [u8; 4],
+    pub opacity: f32,
+    pub show_pores: bool,
+    pub show_material: bool,
+    pub outline_only: bool,
+    pub pore_coloring: PoreColoring,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            pore_color: [0, 255, 13, 204],
+            material_color: [255, 255, 255, 127],
+            opacity: 1.0,
+            show_pores: true,
+            show_material: true,
+            outline_only: false,
+            pore_coloring: PoreColoring::Uniform,
+        }
+    }
+}
+
+impl OverlaySettings {
+    fn with_opacity(&self, [r, g, b]: [u8; 3], alpha: u8) -> image::Rgba<u8> {
+        image::Rgba([r, g, b, (alpha as f32 * self.opacity).round() as u8])
+    }
+
+    pub fn pore_pixel(&self) -> image::Rgba<u8> {
+        let [r, g, b, a] = self.pore_color;
+        self.with_opacity([r, g, b], a)
+    }
+
+    pub fn material_pixel(&self) -> image::Rgba<u8> {
+        let [r, g, b, a] = self.material_color;
+        self.with_opacity([r, g, b], a)
+    }
+
+    pub fn class_pixel(&self, class: PoreClass) -> image::Rgba<u8> {
+        match class {
+            PoreClass::Detected => self.pore_pixel(),
+            PoreClass::FilledFeature => self.with_opacity(FILLED_FEATURE_COLOR, self.pore_color[3]),
+            PoreClass::Manual => self.with_opacity(MANUAL_PORE_COLOR, self.pore_color[3]),
+        }
+    }
+
+    // the sizes are mapped on a log scale, pore areas spread over several orders of magnitude
+    pub fn size_pixel(&self, area: usize, (min_area, max_area): (usize, usize)) -> image::Rgba<u8> {
+        let position = if max_area > min_area {
+            ((area as f64).ln() - (min_area as f64).ln())
+                / ((max_area as f64).ln() - (min_area as f64).ln())
+        } else {
+            0.0
+        };
+
+        self.with_opacity(size_color(position), self.pore_color[3])
+    }
+}
+
+fn size_color(position: f64) -> [u8; 3] {
+    let scaled = position.clamp(0.0, 1.0) * (SIZE_COLOR_MAP.len() - 1) as f64;
+    let index = (scaled.floor() as usize).min(SIZE_COLOR_MAP.len() - 2);
+    let t = scaled - index as f64;
+
+    let (from, to) = (SIZE_COLOR_MAP[index], SIZE_COLOR_MAP[index + 1]);
+    [0, 1, 2].map(|i| (from[i] as f64 + (to[i] as f64 - from[i] as f64) * t).round() as u8)
+}
+
+fn legend_entry(ui: &mut egui::Ui, [r, g, b]: [u8; 3], text: impl Into<String>) {
+    ui.horizontal(|ui| {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::hover());
+        ui.painter()
+            .rect_filled(rect, 2.0, Color32::from_rgb(r, g, b));
+        ui.label(text.into());
+    });
+}
+
+pub fn display_display_settings_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    let mut window_open = app.display_settings_window_open;
+    let mut changed = false;
+
+    egui::Window::new("Display Settings")
+        .open(&mut window_open)
+        .show(ctx, |ui| {
+            let settings = &mut app.overlay_settings;
+
+            egui::Grid::new("display_settings_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    changed |= ui.checkbox(&mut settings.show_pores, "Pores").changed();
+                    changed |= ui
+                        .color_edit_button_srgba_unmultiplied(&mut settings.pore_color)
+                        .changed();
+                    ui.end_row();
+
+                    changed |= ui
+                        .checkbox(&mut settings.show_material, "Material")
+                        .changed();
+                    changed |= ui
+                        .color_edit_button_srgba_unmultiplied(&mut settings.material_color)
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Opacity");
+                    changed |= ui
+                        .add(Slider::new(&mut settings.opacity, 0.0..=1.0))
+                        .changed();
+                    ui.end_row();
+                });
+
+            changed |= ui
+                .checkbox(&mut settings.outline_only, "Only pore outlines")
+                .changed();
+
+            ui.horizontal(|ui| {
+                ui.label("Pore colour:");
+                for (coloring, text) in [
+                    (PoreColoring::Uniform, "Uniform"),
+                    (PoreColoring::BySize, "By size"),
+                    (PoreColoring::ByClass, "By class"),
+                ] {
+                    changed |= ui
+                        .selectable_value(&mut settings.pore_coloring, coloring, text)
+                        .changed();
+                }
+            });
+
+            let pore_color = [
+                settings.pore_color[0],
+                settings.pore_color[1],
+                settings.pore_color[2],
+            ];
+
+            match settings.pore_coloring {
+                PoreColoring::Uniform => {}
+                PoreColoring::BySize => {
+                    let size_range = app
+                        .images
+                        .selected
+                        .and_then(|selected_img| app.images.images[selected_img].pore_size_range());
+
+                    ui.separator();
+                    if let Some((min_area, max_area)) = size_range {
+                        for step in 0..SIZE_COLOR_MAP.len() {
+                            let position = step as f64 / (SIZE_COLOR_MAP.len() - 1) as f64;
+                            let area = (min_area as f64).ln()
+                                + ((max_area as f64).ln() - (min_area as f64).ln()) * position;
+                            legend_entry(ui, size_color(position), format!("{:.0} px", area.exp()));
+                        }
+                    } else {
+                        ui.label("No pores found.");
+                    }
+                }
+                PoreColoring::ByClass => {
+                    ui.separator();
+                    legend_entry(ui, pore_color, "Detected pore");
+                    legend_entry(ui, FILLED_FEATURE_COLOR, "Filled feature");
+                    legend_entry(ui, MANUAL_PORE_COLOR, "Manual pore");
+                }
+            }
+        });
+
+    app.display_settings_window_open = window_open;
+
+    if changed {
+        app.update_image_to_display(ctx);
+    }
+}
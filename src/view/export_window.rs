@@ -3,11 +3,19 @@ use core::fmt;
 use egui::ComboBox;
 use egui_extras::{Column, TableBuilder};
 
-use crate::PoreDetectionApp;
+use crate::{
+    model::{
+        excluded_pores::format_excluded_pores, json_export::export_json,
+        project::show_project_error,
+    },
+    PoreDetectionApp,
+};
 
 pub fn display_export_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    let mut window_open = app.export_window_open;
+
     egui::Window::new("Export Results")
-        .open(&mut app.export_window_open)
+        .open(&mut window_open)
         .max_height(600.0)
         .show(ctx, |ui| {
             let result_table = TableBuilder::new(ui)
@@ -21,6 +29,13 @@ pub fn display_export_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                 .column(Column::initial(150.0))
                 .column(Column::initial(150.0))
                 .column(Column::initial(150.0).clip(true))
+                .column(Column::initial(100.0))
+                .column(Column::initial(150.0))
+                .column(Column::initial(150.0))
+                .column(Column::initial(150.0))
+                .column(Column::initial(150.0))
+                .column(Column::initial(100.0))
+                .column(Column::initial(250.0).clip(true))
                 .header(30.0, |mut header| {
                     header.col(|ui| {
                         ui.heading("Filename");
@@ -46,6 +61,27 @@ pub fn display_export_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                     header.col(|ui| {
                         ui.heading("File Path");
                     });
+                    header.col(|ui| {
+                        ui.heading("Pore Count");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Mean NN Distance");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Mean Spacing");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Clark-Evans Index");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Density Uncertainty");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Manual Pixels");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Excluded Pores");
+                    });
                 });
 
             result_table.body(|body| {
@@ -97,13 +133,72 @@ pub fn display_export_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                             ui.label("No File");
                         }
                     });
+                    row.col(|ui| {
+                        if let Some(pores) = &current_image.pores {
+                            ui.label(format!("{}", pores.len()));
+                        } else {
+                            ui.label("-");
+                        }
+                    });
+
+                    let statistics = current_image.spatial_statistics.as_ref();
+                    row.col(|ui| {
+                        if let Some(statistics) = statistics {
+                            ui.label(format!(
+                                "{:.2} px",
+                                statistics.mean_nearest_neighbour_distance
+                            ));
+                        } else {
+                            ui.label("-");
+                        }
+                    });
+                    row.col(|ui| {
+                        if let Some(statistics) = statistics {
+                            ui.label(format!("{:.2} px", statistics.mean_spacing));
+                        } else {
+                            ui.label("-");
+                        }
+                    });
+                    row.col(|ui| {
+                        if let Some(statistics) = statistics {
+                            ui.label(format!("{:.3}", statistics.clark_evans_index));
+                        } else {
+                            ui.label("-");
+                        }
+                    });
+                    row.col(|ui| match &current_image.threshold_sweep {
+                        Some(sweep) if sweep.matches(&current_image.analysis_parameters()) => {
+                            let levels = app.threshold_sweep.uncertainty_levels;
+                            if let Some(uncertainty) =
+                                sweep.uncertainty(current_image.threshold, levels)
+                            {
+                                ui.label(format!("±{:.5}% (±{})", uncertainty, levels));
+                            } else {
+                                ui.label("-");
+                            }
+                        }
+                        _ => {
+                            ui.label("-");
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{}", current_image.manual_overrides.len()));
+                    });
+                    row.col(|ui| {
+                        if current_image.excluded_pores.is_empty() {
+                            ui.label("-");
+                        } else {
+                            ui.label(format_excluded_pores(&current_image.excluded_pores));
+                        }
+                    });
                 });
             });
 
             ui.separator();
 
             ui.horizontal(|ui| {
-                ui.label("Choose number format:");
+                ui.label("Choose number format:")
+                    .on_hover_text("Used by the CSV export, Excel stores the numbers as numbers");
                 ComboBox::from_id_salt("Number format")
                     .selected_text(format!("{}", app.export_decimal_format))
                     .show_ui(ui, |ui| {
@@ -120,13 +215,41 @@ pub fn display_export_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                     });
 
                 if ui.button("Export Excel").clicked() {
-                    app.images.export(app.export_decimal_format.clone());
+                    app.images.export(
+                        app.project_path.as_deref(),
+                        app.threshold_sweep.uncertainty_levels,
+                    );
+                }
+                if ui.button("Export CSV").clicked() {
+                    app.images.export_csv(
+                        app.export_decimal_format.clone(),
+                        false,
+                        app.threshold_sweep.uncertainty_levels,
+                    );
+                }
+                if ui.button("Export TSV").clicked() {
+                    app.images.export_csv(
+                        app.export_decimal_format.clone(),
+                        true,
+                        app.threshold_sweep.uncertainty_levels,
+                    );
+                }
+                if ui
+                    .button("Export JSON")
+                    .on_hover_text("All results with the pores of every image, the JSON schema is saved next to it")
+                    .clicked()
+                {
+                    if let Err(err) = export_json(app) {
+                        show_project_error(&err);
+                    }
                 }
             })
         });
+
+    app.export_window_open = window_open;
 }
 
-#[derive(Default, PartialEq, Clone)]
+#[derive(Default, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
 pub enum ExportDecimalFormat {
     #[default]
     Dot,
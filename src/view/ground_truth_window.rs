@@ -0,0 +1,128 @@
+use egui::{Color32, Grid};
+use rfd::FileDialog;
+
+use crate::{model::ground_truth::load_ground_truth, PoreDetectionApp};
+
+pub fn display_ground_truth_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    let mut window_open = app.ground_truth_window_open;
+
+    egui::Window::new("Ground Truth")
+        .open(&mut window_open)
+        .show(ctx, |ui| {
+            let Some(selected_img) = app.images.selected else {
+                ui.label("Load an image to attach a ground truth mask.");
+                return;
+            };
+
+            ui.horizontal(|ui| {
+                if ui.button("Attach Mask").clicked() {
+                    let path = FileDialog::new()
+                        .add_filter("Mask", &["png", "tif", "tiff"])
+                        .pick_file();
+
+                    let current_image = &mut app.images.images[selected_img];
+                    if let (Some(path), Some(dimensions)) = (path, current_image.dimensions) {
+                        match load_ground_truth(&path, dimensions) {
+                            Ok(mask) => {
+                                log::info!("Attached ground truth mask: {:?}", path);
+
+                                current_image.ground_truth = Some(mask);
+                                current_image.ground_truth_path = Some(path);
+                                current_image.update_segmentation_comparison();
+                                app.ground_truth_error = None;
+                                app.update_image_to_display(ctx);
+                            }
+                            Err(err) => {
+                                app.ground_truth_error =
+                                    Some(format!("Could not attach the mask: {}", err));
+                            }
+                        }
+                    }
+                }
+
+                if ui.button("Remove Mask").clicked() {
+                    let current_image = &mut app.images.images[selected_img];
+                    current_image.ground_truth = None;
+                    current_image.ground_truth_path = None;
+                    current_image.segmentation_comparison = None;
+                    app.update_image_to_display(ctx);
+                }
+
+                if ui
+                    .checkbox(&mut app.ground_truth_overlay, "Show errors")
+                    .changed()
+                {
+                    app.update_image_to_display(ctx);
+                }
+            });
+
+            if let Some(error) = &app.ground_truth_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+
+            let current_image = &app.images.images[selected_img];
+
+            if let Some(path) = &current_image.ground_truth_path {
+                ui.label(format!("Mask: {}", path.display()));
+            }
+
+            let Some(comparison) = &current_image.segmentation_comparison else {
+                return;
+            };
+            let metrics = &comparison.metrics;
+
+            ui.separator();
+
+            let format_ratio = |ratio: Option<f64>| match ratio {
+                Some(ratio) => format!("{:.4}", ratio),
+                None => "-".to_string(),
+            };
+            let format_percentage = |percentage: Option<f64>| match percentage {
+                Some(percentage) => format!("{:.5}%", percentage),
+                None => "-".to_string(),
+            };
+
+            Grid::new("segmentation_metrics_grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Precision");
+                    ui.label(format_ratio(metrics.precision()));
+                    ui.end_row();
+
+                    ui.label("Recall");
+                    ui.label(format_ratio(metrics.recall()));
+                    ui.end_row();
+
+                    ui.label("IoU");
+                    ui.label(format_ratio(metrics.iou()));
+                    ui.end_row();
+
+                    ui.label("Dice");
+                    ui.label(format_ratio(metrics.dice()));
+                    ui.end_row();
+
+                    ui.label("Segmented pore fraction");
+                    ui.label(format_percentage(metrics.segmented_pore_fraction()));
+                    ui.end_row();
+
+                    ui.label("Ground truth pore fraction");
+                    ui.label(format_percentage(metrics.ground_truth_pore_fraction()));
+                    ui.end_row();
+
+                    ui.label("Absolute pore fraction error");
+                    ui.label(format_percentage(metrics.pore_fraction_error()));
+                    ui.end_row();
+
+                    ui.colored_label(Color32::RED, "False positives");
+                    ui.label(format!("{} px", metrics.false_positives));
+                    ui.end_row();
+
+                    ui.colored_label(Color32::from_rgb(0, 80, 255), "False negatives");
+                    ui.label(format!("{} px", metrics.false_negatives));
+                    ui.end_row();
+                });
+        });
+
+    app.ground_truth_window_open = window_open;
+}
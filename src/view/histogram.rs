@@ -0,0 +1,168 @@
+use egui::{Color32, Ui};
+use egui_plot::{Bar, BarChart, Plot, VLine};
+
+use crate::{model::histogram::AutoThreshold, PoreDetectionApp};
+
+// auto threshold markers catch the dragged threshold line within this many grey levels
+const SNAP_DISTANCE: f64 = 3.0;
+
+// a drag that starts within this many points of a line moves it
+const GRAB_DISTANCE: f32 = 6.0;
+
+#[derive(Default, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct HistogramSettings {
+    pub log_scale: bool,
+}
+
+pub fn display_histogram(ui: &mut Ui, app: &mut PoreDetectionApp) {
+    let Some(selected_img) = app.images.selected else {
+        return;
+    };
+    let current_image = &app.images.images[selected_img];
+    let threshold = current_image.threshold;
+    let Some(histogram) = current_image.histogram().cloned() else {
+        ui.label("The histogram is computed with the analysis.");
+        return;
+    };
+
+    // the auto threshold marker that is dragged, the threshold follows it
+    let grabbed_id = ui.id().with("histogram_grabbed_marker");
+    let mut grabbed = ui.data(|data| data.get_temp::<Option<AutoThreshold>>(grabbed_id).flatten());
+
+    let auto_thresholds = AutoThreshold::ALL
+        .iter()
+        .filter_map(|method| Some((*method, histogram.auto_threshold(*method)?)))
+        .collect::<Vec<_>>();
+
+    let log_scale = app.histogram_settings.log_scale;
+    let bar = |level: usize, count: usize| {
+        let height = if log_scale {
+            (count as f64 + 1.0).log10()
+        } else {
+            count as f64
+        };
+        Bar::new(level as f64, height).width(1.0)
+    };
+    let (pore_bars, material_bars): (Vec<_>, Vec<_>) = histogram
+        .counts
+        .iter()
+        .enumerate()
+        .map(|(level, count)| (level, bar(level, *count)))
+        .partition(|(level, _)| *level as i16 <= threshold);
+
+    let plot_response = Plot::new("histogram_plot")
+        .height(120.0)
+        .show_axes([true, false])
+        .show_grid(false)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .allow_double_click_reset(false)
+        .include_x(0.0)
+        .include_x(255.0)
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(
+                BarChart::new(pore_bars.into_iter().map(|(_, bar)| bar).collect())
+                    .color(Color32::from_rgb(0, 255, 13))
+                    .name("Pore"),
+            );
+            plot_ui.bar_chart(
+                BarChart::new(material_bars.into_iter().map(|(_, bar)| bar).collect())
+                    .color(Color32::GRAY)
+                    .name("Material"),
+            );
+
+            for (method, level) in &auto_thresholds {
+                let line = if grabbed == Some(*method) {
+                    VLine::new(threshold as f64).width(2.0)
+                } else {
+                    VLine::new(*level as f64).style(egui_plot::LineStyle::dashed_dense())
+                };
+                plot_ui.vline(line.color(Color32::LIGHT_BLUE).name(method.to_string()));
+            }
+
+            plot_ui.vline(
+                VLine::new(threshold as f64)
+                    .color(Color32::RED)
+                    .width(2.0)
+                    .name("Threshold"),
+            );
+        });
+
+    let response = &plot_response.response;
+    if response.drag_started_by(egui::PointerButton::Primary) {
+        grabbed = response.interact_pointer_pos().and_then(|pointer| {
+            let distance = |level: i16| {
+                (plot_response.transform.position_from_point_x(level as f64) - pointer.x).abs()
+            };
+            // the threshold line wins over a marker at the same position
+            if distance(threshold) <= GRAB_DISTANCE {
+                return None;
+            }
+            auto_thresholds
+                .iter()
+                .filter(|(_, level)| distance(*level) <= GRAB_DISTANCE)
+                .min_by(|(_, a), (_, b)| distance(*a).total_cmp(&distance(*b)))
+                .map(|(method, _)| *method)
+        });
+    }
+
+    if response.dragged_by(egui::PointerButton::Primary) || response.clicked() {
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let x = plot_response.transform.value_from_position(pointer).x;
+
+            // a dragged marker does not snap to the others
+            let snapped = match grabbed {
+                Some(_) => x,
+                None => auto_thresholds
+                    .iter()
+                    .map(|(_, level)| *level as f64)
+                    .find(|level| (level - x).abs() <= SNAP_DISTANCE)
+                    .unwrap_or(x),
+            };
+
+            // only a preview is analyzed until the line is released
+            let snapped = snapped.round().clamp(0.0, 255.0) as i16;
+            if snapped != app.images.images[selected_img].threshold {
+                app.images.images[selected_img].threshold = snapped;
+                if response.dragged() {
+                    app.preview_image(selected_img);
+                }
+            }
+        }
+    }
+    if response.drag_stopped() || response.clicked() {
+        grabbed = None;
+        log::info!(
+            "threshold from histogram: {}",
+            app.images.images[selected_img].threshold
+        );
+        app.reload_image(Some(selected_img));
+    }
+    ui.data_mut(|data| data.insert_temp(grabbed_id, grabbed));
+
+    ui.horizontal(|ui| {
+        let pore_share = histogram.pore_share(threshold).unwrap_or(0.0);
+        ui.label(format!(
+            "Pore: {:.2}%  Material: {:.2}%",
+            pore_share,
+            100.0 - pore_share
+        ));
+        ui.checkbox(&mut app.histogram_settings.log_scale, "Log");
+    });
+
+    ui.horizontal(|ui| {
+        for (method, level) in &auto_thresholds {
+            if ui
+                .button(format!("{}: {}", method, level))
+                .on_hover_text("Use this threshold")
+                .clicked()
+            {
+                app.images.images[selected_img].threshold = *level;
+                app.reload_image(Some(selected_img));
+            }
+        }
+    });
+}
@@ -0,0 +1,62 @@
+use egui::ScrollArea;
+
+use crate::{
+    model::history::{jump_to, redo, undo},
+    PoreDetectionApp,
+};
+
+pub fn display_history_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    let mut open = app.history_window_open;
+    // number of applied entries to jump to, the list is borrowed while it is shown
+    let mut jump = None;
+
+    egui::Window::new("History")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!app.history.undo.is_empty(), egui::Button::new("Undo"))
+                    .clicked()
+                {
+                    undo(app, ctx);
+                }
+                if ui
+                    .add_enabled(!app.history.redo.is_empty(), egui::Button::new("Redo"))
+                    .clicked()
+                {
+                    redo(app, ctx);
+                }
+            });
+
+            ui.label("Click an entry to go back to the state after it.");
+            ui.separator();
+
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                let applied = app.history.undo.len();
+
+                if ui.selectable_label(applied == 0, "Start").clicked() {
+                    jump = Some(0);
+                }
+                for (i, entry) in app.history.undo.iter().enumerate() {
+                    if ui
+                        .selectable_label(i + 1 == applied, &entry.label)
+                        .clicked()
+                    {
+                        jump = Some(i + 1);
+                    }
+                }
+                // undone entries are shown greyed out until a new change replaces them
+                for (i, entry) in app.history.redo.iter().rev().enumerate() {
+                    let label = egui::RichText::new(&entry.label).weak();
+                    if ui.selectable_label(false, label).clicked() {
+                        jump = Some(applied + i + 1);
+                    }
+                }
+            });
+        });
+
+    app.history_window_open = open;
+    if let Some(undo_count) = jump {
+        jump_to(app, ctx, undo_count);
+    }
+}
@@ -0,0 +1,269 @@
+use core::fmt;
+
+use egui::ComboBox;
+use rfd::FileDialog;
+
+use crate::{
+    model::{
+        mask_export::export_masks,
+        overlay::{export_file_name, export_names, render_image, save_image},
+        project::show_project_error,
+    },
+    PoreDetectionApp,
+};
+
+#[derive(Default, PartialEq, Clone, Copy)]
+pub enum ImageLayout {
+    Original,
+    #[default]
+    Overlay,
+    // the original on the left, the overlay on the right
+    SideBySide,
+}
+
+impl fmt::Display for ImageLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageLayout::Original => write!(f, "Original"),
+            ImageLayout::Overlay => write!(f, "Overlay"),
+            ImageLayout::SideBySide => write!(f, "Side by Side"),
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Clone, Copy)]
+pub enum ExportImageFormat {
+    #[default]
+    Png,
+    Tiff,
+    Jpeg,
+}
+
+impl ExportImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportImageFormat::Png => "png",
+            ExportImageFormat::Tiff => "tif",
+            ExportImageFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+impl fmt::Display for ExportImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportImageFormat::Png => write!(f, "PNG"),
+            ExportImageFormat::Tiff => write!(f, "TIFF"),
+            ExportImageFormat::Jpeg => write!(f, "JPEG"),
+        }
+    }
+}
+
+// the overlay itself is drawn with the display settings
+pub struct ImageExportSettings {
+    pub layout: ImageLayout,
+    pub format: ExportImageFormat,
+    pub roi_outline: bool,
+    pub scale_bar: bool,
+    pub caption: bool,
+}
+
+impl Default for ImageExportSettings {
+    fn default() -> Self {
+        Self {
+            layout: ImageLayout::default(),
+            format: ExportImageFormat::default(),
+            roi_outline: true,
+            scale_bar: true,
+            caption: true,
+        }
+    }
+}
+
+fn export_selected(app: &PoreDetectionApp, index: usize) {
+    let image = &app.images.images[index];
+    let settings = &app.image_export_settings;
+    let extension = settings.format.extension();
+    let name = &export_names(&app.images.images)[index];
+
+    let path = FileDialog::new()
+        .add_filter(settings.format.to_string(), &[extension])
+        .set_file_name(export_file_name(name, "overlay", extension).to_string_lossy())
+        .save_file();
+    let Some(path) = path else {
+        return;
+    };
+
+    let result = render_image(
+        image,
+        &app.overlay_settings,
+        app.ground_truth_overlay,
+        settings,
+    )
+    .and_then(|rendered| save_image(&rendered, &path, settings.format));
+    if let Err(err) = result {
+        show_project_error(&err);
+    }
+}
+
+// images that can't be rendered are skipped and listed afterwards
+fn export_all(app: &PoreDetectionApp) {
+    let Some(folder) = FileDialog::new().pick_folder() else {
+        return;
+    };
+    let settings = &app.image_export_settings;
+    let extension = settings.format.extension();
+
+    let mut problems = Vec::new();
+    let names = export_names(&app.images.images);
+    for (image, name) in app.images.images.iter().zip(&names) {
+        let path = folder.join(export_file_name(name, "overlay", extension));
+        let result = render_image(
+            image,
+            &app.overlay_settings,
+            app.ground_truth_overlay,
+            settings,
+        )
+        .and_then(|rendered| save_image(&rendered, &path, settings.format));
+
+        if let Err(err) = result {
+            let name = image
+                .path
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .unwrap_or_default();
+            problems.push(format!("{:?}: {}", name, err));
+        }
+    }
+    log::info!(
+        "Exported {} of {} images to {:?}",
+        app.images.images.len() - problems.len(),
+        app.images.images.len(),
+        folder
+    );
+
+    if !problems.is_empty() {
+        rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Warning)
+            .set_title("Export Images")
+            .set_description(format!(
+                "Some images were not exported:\n{}",
+                problems.join("\n")
+            ))
+            .show();
+    }
+}
+
+// `None` exports the masks of every image
+fn export_all_masks(app: &PoreDetectionApp, index: Option<usize>) {
+    let Some(folder) = FileDialog::new().pick_folder() else {
+        return;
+    };
+    // the same names as in an export of all images
+    let names = export_names(&app.images.images);
+    let range = match index {
+        Some(index) => index..index + 1,
+        None => 0..app.images.images.len(),
+    };
+
+    let problems = app.images.images[range.clone()]
+        .iter()
+        .zip(&names[range])
+        .filter_map(|(image, name)| {
+            let file_name = image
+                .path
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .unwrap_or_default();
+            // skipped label maps are reported like errors
+            match export_masks(image, name, &folder) {
+                Ok(warning) => warning,
+                Err(err) => Some(format!("not exported, {}", err)),
+            }
+            .map(|problem| format!("{:?}: {}", file_name, problem))
+        })
+        .collect::<Vec<_>>();
+
+    if !problems.is_empty() {
+        rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Warning)
+            .set_title("Export Masks")
+            .set_description(problems.join("\n"))
+            .show();
+    }
+}
+
+pub fn display_image_export_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    let mut window_open = app.image_export_window_open;
+
+    egui::Window::new("Export Images")
+        .open(&mut window_open)
+        .show(ctx, |ui| {
+            let Some(selected_img) = app.images.selected else {
+                ui.label("Load images to export them.");
+                return;
+            };
+            let settings = &mut app.image_export_settings;
+
+            ui.horizontal(|ui| {
+                ui.label("Layout:");
+                ComboBox::from_id_salt("Image layout")
+                    .selected_text(settings.layout.to_string())
+                    .show_ui(ui, |ui| {
+                        for layout in [
+                            ImageLayout::Original,
+                            ImageLayout::Overlay,
+                            ImageLayout::SideBySide,
+                        ] {
+                            ui.selectable_value(&mut settings.layout, layout, layout.to_string());
+                        }
+                    });
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Format:");
+                ComboBox::from_id_salt("Image format")
+                    .selected_text(settings.format.to_string())
+                    .show_ui(ui, |ui| {
+                        for format in [
+                            ExportImageFormat::Png,
+                            ExportImageFormat::Tiff,
+                            ExportImageFormat::Jpeg,
+                        ] {
+                            ui.selectable_value(&mut settings.format, format, format.to_string());
+                        }
+                    });
+            });
+
+            ui.checkbox(&mut settings.roi_outline, "Region outline");
+            ui.checkbox(&mut settings.scale_bar, "Scale bar")
+                .on_hover_text("In pixels, there is no calibration yet");
+            ui.checkbox(&mut settings.caption, "Caption with filename and density");
+            ui.label("The overlay is drawn with the display settings.");
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Export Selected").clicked() {
+                    export_selected(app, selected_img);
+                }
+                if ui.button("Export All").clicked() {
+                    export_all(app);
+                }
+            });
+
+            ui.separator();
+
+            ui.label("Segmentation as a pore mask, a material mask and a label map (TIFF) for other tools:");
+            ui.horizontal(|ui| {
+                if ui.button("Export Masks").clicked() {
+                    export_all_masks(app, Some(selected_img));
+                }
+                if ui.button("Export All Masks").clicked() {
+                    export_all_masks(app, None);
+                }
+            });
+        });
+
+    app.image_export_window_open = window_open;
+}
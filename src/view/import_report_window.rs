@@ -0,0 +1,31 @@
+use egui::ScrollArea;
+
+use crate::PoreDetectionApp;
+
+// problems of the last Excel or CSV import, the valid rows are loaded anyway
+pub fn display_import_report_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    if app.import_problems.is_empty() {
+        return;
+    }
+    let mut open = true;
+
+    egui::Window::new("Import Problems")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label("Some parts of the file could not be imported:");
+
+            ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for problem in &app.import_problems {
+                    ui.label(problem);
+                }
+            });
+
+            if ui.button("Close").clicked() {
+                app.import_problems.clear();
+            }
+        });
+
+    if !open {
+        app.import_problems.clear();
+    }
+}
@@ -1,8 +1,20 @@
+pub mod batch_window;
 pub mod debug_window;
+pub mod depth_profile_window;
+pub mod display_settings_window;
 pub mod export_window;
+pub mod ground_truth_window;
+pub mod histogram;
+pub mod history_window;
+pub mod image_export_window;
+pub mod import_report_window;
 pub mod load_project_modal;
 pub mod new_project_modal;
 pub mod plot;
+pub mod relink_modal;
+pub mod restore_session_modal;
 pub mod shortcut_window;
 pub mod sidepanel;
+pub mod statistics_window;
+pub mod threshold_sweep_window;
 pub mod top_panel;
@@ -3,9 +3,38 @@ use egui::{
     Color32, Stroke, Ui, Vec2,
 };
 use egui_plot::{Line, PlotImage, PlotPoint, PlotPoints, PlotResponse};
-use image::GenericImageView;
 
-use crate::PoreDetectionApp;
+use crate::{
+    model::{
+        depth_profile::ReferenceLine,
+        excluded_pores::ExcludedPore,
+        image_data::{Pore, PoreSize},
+        manual_mask::{paint_circle, ManualOverride},
+    },
+    PoreDetectionApp,
+};
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Deserialize, serde::Serialize)]
+pub enum PlotTool {
+    #[default]
+    Region,
+    ReferenceLine,
+    Brush,
+    Eraser,
+    PickPore,
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct BrushSettings {
+    pub radius: u32,
+}
+
+impl Default for BrushSettings {
+    fn default() -> Self {
+        Self { radius: 5 }
+    }
+}
 
 pub fn display_plot(ctx: &egui::Context, app: &mut PoreDetectionApp) {
     egui::CentralPanel::default().show(ctx, |ui| {
@@ -32,17 +61,26 @@ pub fn display_plot(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                 }
 
                 if let Some(handle) = &app.image_to_display {
+                    // the texture of a preview is smaller than the image and is stretched to its size
+                    let image_size = app
+                        .images
+                        .selected
+                        .and_then(|selected| app.images.images[selected].dimensions)
+                        .map_or(handle.size_vec2(), |(width, height)| {
+                            Vec2::new(width as f32, height as f32)
+                        });
+
                     plot_ui.add(PlotImage::new(
                         handle.id(),
-                        PlotPoint::new(handle.size_vec2().x / 2.0, handle.size_vec2().y / 2.0),
-                        Vec2::new(handle.size_vec2().x, handle.size_vec2().y),
+                        PlotPoint::new(image_size.x / 2.0, image_size.y / 2.0),
+                        image_size,
                     ));
 
                     if let Some(grayscale_debug_handle) = &app.debug_info.grayscale_handle {
                         plot_ui.add(PlotImage::new(
                             grayscale_debug_handle.id(),
                             PlotPoint::new(
-                                handle.size_vec2().x + grayscale_debug_handle.size_vec2().x / 2.0,
+                                image_size.x + grayscale_debug_handle.size_vec2().x / 2.0,
                                 grayscale_debug_handle.size_vec2().y / 2.0,
                             ),
                             Vec2::new(
@@ -56,8 +94,7 @@ pub fn display_plot(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                         plot_ui.add(PlotImage::new(
                             grayscale_thresh_handle.id(),
                             PlotPoint::new(
-                                (handle.size_vec2().x * 2.0)
-                                    + grayscale_thresh_handle.size_vec2().x / 2.0,
+                                (image_size.x * 2.0) + grayscale_thresh_handle.size_vec2().x / 2.0,
                                 grayscale_thresh_handle.size_vec2().y / 2.0,
                             ),
                             Vec2::new(
@@ -84,13 +121,231 @@ pub fn display_plot(ctx: &egui::Context, app: &mut PoreDetectionApp) {
 
                         plot_ui.line(Line::new(rect_plot_points));
                     }
+
+                    if let Some(ReferenceLine::Custom(line_start, line_end)) =
+                        app.images.images[selected_img].reference_line
+                    {
+                        let line_plot_points: PlotPoints<'_> =
+                            egui_plot::PlotPoints::Owned(Vec::from([line_start, line_end]));
+
+                        plot_ui.line(
+                            Line::new(line_plot_points)
+                                .color(Color32::ORANGE)
+                                .width(2.0),
+                        );
+                    }
                 }
             });
 
-        region_selection(app, ui, &plot_response);
+        match app.plot_tool {
+            PlotTool::Region | PlotTool::ReferenceLine => region_selection(app, ui, &plot_response),
+            PlotTool::Brush | PlotTool::Eraser => mask_editing(app, ui, &plot_response),
+            PlotTool::PickPore => pore_picking(app, &plot_response),
+        }
+
+        if !plot_response.response.dragged() {
+            hover_inspector(app, &plot_response);
+        }
     });
 }
 
+pub fn hover_inspector(app: &PoreDetectionApp, plot_response: &PlotResponse<()>) {
+    let Some(hover_pos) = plot_response.response.hover_pos() else {
+        return;
+    };
+    let Some(current_image) = app.images.images.get(app.images.selected.unwrap_or(0)) else {
+        return;
+    };
+    let Some((width, height)) = current_image.dimensions else {
+        return;
+    };
+
+    // the plot has the y axis pointing up, the image pointing down
+    let position = plot_response.transform.value_from_position(hover_pos);
+    let (x, y) = (position.x, height as f64 - position.y);
+    if x < 0.0 || y < 0.0 || x >= width as f64 || y >= height as f64 {
+        return;
+    }
+    let (x, y) = (x as u32, y as u32);
+
+    plot_response.response.clone().on_hover_ui_at_pointer(|ui| {
+        ui.label(format!("Pixel: ({}, {})", x, y));
+        // large TIFF images are not in memory
+        if let Some(grey_value) = current_image.grey_value(x, y) {
+            ui.label(format!("Grey value: {}", grey_value));
+            ui.label(format!(
+                "Thresholded: {}",
+                if grey_value as i16 > current_image.threshold {
+                    "material"
+                } else {
+                    "pore"
+                }
+            ));
+        }
+
+        if let Some(value) = current_image.manual_overrides.get(&(x, y)) {
+            ui.label(format!("Manual override: {}", value));
+        }
+
+        // the tiled analysis does not keep the pixel groups
+        if current_image.is_large() {
+            ui.separator();
+            ui.label("Pixel groups are not available for tiled images");
+            return;
+        }
+
+        let (Some(labels), Some(label_sizes)) = (&current_image.labels, &current_image.label_sizes)
+        else {
+            return;
+        };
+        let label = labels.get_pixel(x, y)[0];
+        if label == 0 {
+            return;
+        }
+
+        let area = label_sizes[label as usize];
+
+        ui.separator();
+        ui.label(format!("Label: {}", label));
+        ui.label(format!("Area: {} px", area));
+        ui.label(format!(
+            "Equivalent diameter: {:.2} px",
+            (4.0 * area as f64 / std::f64::consts::PI).sqrt()
+        ));
+
+        let is_matched = |pores: &Option<Vec<Pore>>| {
+            pores
+                .as_ref()
+                .is_some_and(|pores| pores.iter().any(|pore| pore.label == label))
+        };
+        let outside_region = current_image
+            .analysis_parameters()
+            .image_region(height)
+            .is_some_and(|(x_start, x_end, y_start, y_end)| {
+                x < x_start || x > x_end || y < y_start || y > y_end
+            });
+
+        let status = match current_image.analysis_parameters().pore_size(area) {
+            PoreSize::TooSmall => "too small",
+            PoreSize::TooLarge => "too large",
+            PoreSize::Passed if outside_region => "outside of the region",
+            PoreSize::Passed if is_matched(&current_image.matched_excluded_pores) => "excluded",
+            PoreSize::Passed if is_matched(&current_image.pores) => "passed",
+            PoreSize::Passed => "not analyzed",
+        };
+        ui.label(format!("Size filter: {}", status));
+    });
+}
+
+pub fn pore_picking(app: &mut PoreDetectionApp, plot_response: &PlotResponse<()>) {
+    if !plot_response.response.clicked() {
+        return;
+    }
+    let Some(hover_pos) = plot_response.response.hover_pos() else {
+        return;
+    };
+
+    let selected_img = app.images.selected.unwrap_or(0);
+    let Some(current_image) = app.images.images.get_mut(selected_img) else {
+        return;
+    };
+    // tiled images have no labels, the side panel says so
+    let Some(labels) = &current_image.labels else {
+        return;
+    };
+
+    // the plot has the y axis pointing up, the image pointing down
+    let position = plot_response.transform.value_from_position(hover_pos);
+    let (x, y) = (position.x, labels.height() as f64 - position.y);
+    if x < 0.0 || y < 0.0 || x >= labels.width() as f64 || y >= labels.height() as f64 {
+        return;
+    }
+
+    let label = labels.get_pixel(x as u32, y as u32)[0];
+    if label == 0 {
+        return;
+    }
+
+    let find_pore = |pores: &Option<Vec<Pore>>| {
+        pores
+            .as_ref()
+            .and_then(|pores| pores.iter().find(|pore| pore.label == label).cloned())
+    };
+
+    if let Some(pore) = find_pore(&current_image.pores) {
+        log::info!("Exclude pore: {:?}", pore);
+        current_image
+            .excluded_pores
+            .push(ExcludedPore::from_pore(&pore));
+    } else if let Some(pore) = find_pore(&current_image.matched_excluded_pores) {
+        log::info!("Include pore: {:?}", pore);
+        current_image
+            .excluded_pores
+            .retain(|excluded_pore| !excluded_pore.matches(&pore));
+    } else {
+        return;
+    }
+
+    app.reload_image(Some(selected_img));
+}
+
+pub fn mask_editing(app: &mut PoreDetectionApp, ui: &mut Ui, plot_response: &PlotResponse<()>) {
+    let Some(hover_pos) = plot_response.response.hover_pos() else {
+        return;
+    };
+    let selected_img = app.images.selected.unwrap_or(0);
+    let Some(current_image) = app.images.images.get_mut(selected_img) else {
+        return;
+    };
+    let Some((width, height)) = current_image.dimensions else {
+        return;
+    };
+
+    // show the size of the brush around the cursor
+    let screen_radius = plot_response.transform.dpos_dvalue_x() as f32 * app.brush.radius as f32;
+    ui.painter().circle_stroke(
+        hover_pos,
+        screen_radius.abs().max(1.0),
+        Stroke::new(1.5, Color32::YELLOW),
+    );
+
+    if plot_response
+        .response
+        .dragged_by(egui::PointerButton::Secondary)
+    {
+        let value = if app.plot_tool == PlotTool::Brush {
+            ManualOverride::Pore
+        } else {
+            ManualOverride::Material
+        };
+
+        // the plot has the y axis pointing up, the image pointing down
+        let position = plot_response.transform.value_from_position(hover_pos);
+        let center = PlotPoint::new(position.x, height as f64 - position.y);
+
+        app.history.begin_stroke(current_image);
+        paint_circle(
+            &mut current_image.manual_overrides,
+            center,
+            app.brush.radius,
+            value,
+            width,
+            height,
+        );
+    }
+
+    if plot_response.response.drag_stopped() {
+        let label = if app.plot_tool == PlotTool::Brush {
+            "Brush"
+        } else {
+            "Eraser"
+        };
+        app.history
+            .end_stroke(&app.images.images[selected_img], label);
+        app.reload_image(Some(selected_img));
+    }
+}
+
 pub fn region_selection(app: &mut PoreDetectionApp, ui: &mut Ui, plot_response: &PlotResponse<()>) {
     if app.region_selector.0.is_none()
         && plot_response.response.drag_started()
@@ -106,28 +361,31 @@ pub fn region_selection(app: &mut PoreDetectionApp, ui: &mut Ui, plot_response:
     }
 
     if let (Some(start), Some(end)) = (app.region_selector.0, app.region_selector.1) {
-        let rect = epaint::Rect::from_two_pos(start, end);
-        let selected_region = epaint::RectShape::stroke(
-            rect,
-            0.0,
-            Stroke::new(2.5, Color32::GREEN),
-            egui::StrokeKind::Middle,
-        );
-        ui.painter().rect_stroke(
-            selected_region.rect,
-            selected_region.corner_radius,
-            selected_region.stroke,
-            selected_region.stroke_kind,
-        );
+        if app.plot_tool == PlotTool::ReferenceLine {
+            ui.painter()
+                .line_segment([start, end], Stroke::new(2.5, Color32::ORANGE));
+        } else {
+            let rect = epaint::Rect::from_two_pos(start, end);
+            let selected_region = epaint::RectShape::stroke(
+                rect,
+                0.0,
+                Stroke::new(2.5, Color32::GREEN),
+                egui::StrokeKind::Middle,
+            );
+            ui.painter().rect_stroke(
+                selected_region.rect,
+                selected_region.corner_radius,
+                selected_region.stroke,
+                selected_region.stroke_kind,
+            );
+        }
 
         if plot_response.response.drag_stopped() {
             let start = plot_response.transform.value_from_position(start);
             let end = plot_response.transform.value_from_position(end);
 
             let selected_img = app.images.selected.unwrap_or(0);
-            if let Some(img) = &app.images.images[selected_img].image {
-                let size = img.dimensions();
-
+            if let Some(size) = app.images.images[selected_img].dimensions {
                 let start = PlotPoint::new(
                     start.x.clamp(0.0, size.0 as f64),
                     start.y.clamp(0.0, size.1 as f64),
@@ -137,10 +395,18 @@ pub fn region_selection(app: &mut PoreDetectionApp, ui: &mut Ui, plot_response:
                     end.y.clamp(0.0, size.1 as f64),
                 );
 
-                app.images.images[selected_img].region_start = Some(start);
-                app.images.images[selected_img].region_end = Some(end);
+                if app.plot_tool == PlotTool::ReferenceLine {
+                    app.images.images[selected_img].reference_line =
+                        Some(ReferenceLine::Custom(start, end));
+                    app.images.images[selected_img]
+                        .update_depth_profile(app.depth_profile_settings.band_width);
+                    app.plot_tool = PlotTool::Region;
+                } else {
+                    app.images.images[selected_img].region_start = Some(start);
+                    app.images.images[selected_img].region_end = Some(end);
 
-                app.reload_image(Some(selected_img));
+                    app.reload_image(Some(selected_img));
+                }
             }
 
             app.region_selector.0 = None;
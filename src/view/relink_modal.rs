@@ -0,0 +1,83 @@
+use egui::{Modal, ScrollArea};
+
+use crate::model::{
+    detection_app,
+    project::{finish_opening_project, show_project_error},
+};
+
+pub fn display_relink_modal(ctx: &egui::Context, app: &mut detection_app::PoreDetectionApp) {
+    let Some(mut pending) = app.pending_project.take() else {
+        return;
+    };
+    let mut keep_open = true;
+    let mut open = false;
+
+    Modal::new("relink_modal".into()).show(ctx, |ui| {
+        ui.heading("Missing images");
+
+        let missing = pending.missing();
+        ui.label(format!(
+            "{} of {} images of the project could not be found.",
+            missing.len(),
+            pending.project.images.len()
+        ));
+        ui.label("Search a folder to relink all of them at once, or locate them one by one.");
+
+        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            for i in &missing {
+                ui.horizontal(|ui| {
+                    if ui.button("Locate").clicked() {
+                        let image = &mut pending.project.images[*i];
+                        let mut dialog = rfd::FileDialog::new();
+                        if let Some(name) = image.path.file_name() {
+                            dialog = dialog.set_file_name(name.to_string_lossy());
+                        }
+                        if let Some(path) = dialog.pick_file() {
+                            image.path = path;
+                        }
+                    }
+                    ui.label(pending.project.images[*i].path.to_string_lossy());
+                });
+            }
+        });
+
+        ui.checkbox(&mut pending.match_contents, "Match file contents")
+            .on_hover_text("Also finds renamed images, projects saved by older versions can only be matched by name");
+
+        if ui.button("Search Folder").clicked() {
+            if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                if pending.relink_in_folder(&folder) == 0 {
+                    show_project_error(&format!("No missing images found in {:?}", folder));
+                }
+            }
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("Cancel").clicked() {
+                keep_open = false;
+            }
+
+            ui.add_space(8.0);
+
+            if ui.button("Open without missing images").clicked() {
+                pending.remove_missing();
+                open = true;
+            }
+
+            if ui
+                .add_enabled(pending.missing().is_empty(), egui::Button::new("Open"))
+                .clicked()
+            {
+                open = true;
+            }
+        });
+    });
+
+    if open {
+        finish_opening_project(ctx, app, pending);
+    } else if keep_open {
+        app.pending_project = Some(pending);
+    }
+}
@@ -0,0 +1,49 @@
+use egui::Modal;
+
+use crate::model::{detection_app, project::open_project, relink::PendingProject};
+
+pub fn display_restore_session_modal(
+    ctx: &egui::Context,
+    app: &mut detection_app::PoreDetectionApp,
+) {
+    let Some(session) = &app.session_to_restore else {
+        return;
+    };
+    let mut restore = false;
+    let mut discard = false;
+
+    Modal::new("restore_session_modal".into()).show(ctx, |ui| {
+        ui.heading("Restore last session?");
+
+        ui.label(format!(
+            "The last session with {} images was not saved.",
+            session.project.images.len()
+        ));
+        match &session.project_path {
+            Some(path) => ui.label(format!("It has changes that are not in {:?}.", path)),
+            None => ui.label("It was never saved to a project file."),
+        };
+
+        ui.horizontal(|ui| {
+            if ui.button("Discard").clicked() {
+                discard = true;
+            }
+
+            ui.add_space(8.0);
+
+            if ui.button("Restore").clicked() {
+                restore = true;
+            }
+        });
+    });
+
+    if discard {
+        app.session_to_restore = None;
+        app.discard_autosave = true;
+    } else if restore {
+        let session = app.session_to_restore.take().unwrap();
+        let mut pending = PendingProject::new(session.project, session.project_path);
+        pending.saved = false;
+        open_project(ctx, app, pending);
+    }
+}
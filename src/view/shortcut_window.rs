@@ -9,7 +9,12 @@ pub fn display_shortcut_window(ctx: &egui::Context, app: &mut PoreDetectionApp)
                 ui.label("Left Arrow: Previous image");
                 ui.label("Right Arrow: Next image");
                 ui.label("Enter: Apply region from previous image to current image");
+                ui.label("Ctrl + S: Save the project");
+                ui.label("Ctrl + Z: Undo");
+                ui.label("Ctrl + Shift + Z: Redo");
                 ui.label("Drag right mouse button: Select region (needs to be from top-left to bottom-right!)");
+                ui.label("Drag right mouse button with \"Draw line\" active: Draw the reference line for the depth profile");
+                ui.label("Left click with \"Pick Pore\" active: Exclude a pore or include it again");
                 ui.label("Scroll wheel: Zoom in/out");
                 ui.label("Double click left mouse button: Reset zoom and center image");
             });
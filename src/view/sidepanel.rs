@@ -3,7 +3,13 @@ use egui_extras::{Column, TableBuilder};
 use rfd::FileDialog;
 
 use crate::{
-    model::{detection_app::load_texture_into_ctx, image_data::ImageData},
+    model::{
+        history::{add_images, remove_image},
+        image_data::ImageData,
+        mask_export::import_masks,
+        project::show_project_error,
+    },
+    view::{histogram::display_histogram, plot::PlotTool},
     PoreDetectionApp,
 };
 
@@ -14,6 +20,8 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
         .show(ctx, |ui| {
             ui.heading("Options");
 
+            display_histogram(ui, app);
+
             TableBuilder::new(ui)
                 .id_salt("options_table")
                 .column(Column::auto())
@@ -66,11 +74,10 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                                 }
 
                                 if response.changed() {
-                                    // [TODO] use channels differently bc changing the value will create a new channel
-                                    //        and the old receiver will be dropped, so the thread is sending on a closed
-                                    //        channel
                                     app.images.images[app.images.selected.unwrap_or(0)].threshold =
                                         threshold;
+                                    reload_or_preview(app, &response);
+                                } else if response.drag_stopped() {
                                     app.reload_image(app.images.selected);
                                 }
                             });
@@ -108,12 +115,14 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
 
                                 if response.changed() {
                                     app.images.images[selected_i] = current_image.clone();
-                                    app.reload_image(app.images.selected);
+                                    reload_or_preview(app, &response);
 
                                     log::info!(
                                         "included min feature size: {}",
                                         current_image.included_min_feature_size
                                     );
+                                } else if response.drag_stopped() {
+                                    app.reload_image(app.images.selected);
                                 }
                             });
                         });
@@ -156,13 +165,15 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
 
                             if response.inner.changed() {
                                 app.images.images[selected_i] = current_image.clone();
-                                app.reload_image(app.images.selected);
+                                reload_or_preview(app, &response.inner);
 
                                 log::info!(
                                     "min pore size bounds: {} - {}",
                                     current_image.minimal_pore_size_low,
                                     current_image.minimal_pore_size_high
                                 );
+                            } else if response.inner.drag_stopped() {
+                                app.reload_image(app.images.selected);
                             }
                         });
                     });
@@ -204,13 +215,15 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
 
                             if response.inner.changed() {
                                 app.images.images[selected_i] = current_image.clone();
-                                app.reload_image(app.images.selected);
+                                reload_or_preview(app, &response.inner);
 
                                 log::info!(
                                     "min pore size bounds: {} - {}",
                                     current_image.minimal_pore_size_low,
                                     current_image.minimal_pore_size_high
                                 );
+                            } else if response.inner.drag_stopped() {
+                                app.reload_image(app.images.selected);
                             }
                         });
                     });
@@ -246,14 +259,97 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                 },
             );
 
+            ui.horizontal(|ui| {
+                ui.label("Tool:");
+                ui.selectable_value(&mut app.plot_tool, PlotTool::Region, "Region");
+                ui.selectable_value(&mut app.plot_tool, PlotTool::Brush, "Brush")
+                    .on_hover_text("Drag with the right mouse button to force pixels to be pores");
+                ui.selectable_value(&mut app.plot_tool, PlotTool::Eraser, "Eraser")
+                    .on_hover_text(
+                        "Drag with the right mouse button to force pixels to be material",
+                    );
+
+                if matches!(app.plot_tool, PlotTool::Brush | PlotTool::Eraser) {
+                    ui.add(
+                        DragValue::new(&mut app.brush.radius)
+                            .range(0..=200)
+                            .prefix("Radius: ")
+                            .suffix(" px"),
+                    );
+                }
+
+                ui.selectable_value(&mut app.plot_tool, PlotTool::PickPore, "Pick Pore")
+                    .on_hover_text(
+                        "Click on a pore to exclude it from the analysis or include it again",
+                    );
+                let is_large = app
+                    .images
+                    .selected
+                    .is_some_and(|selected_img| app.images.images[selected_img].is_large());
+                if app.plot_tool == PlotTool::PickPore && is_large {
+                    ui.label("(not available for tiled images)").on_hover_text(
+                        "The pixel groups of images with more than 8192 × 8192 pixels are not kept",
+                    );
+                }
+
+                if let Some(selected_img) = app.images.selected {
+                    let manual_pixels = app.images.images[selected_img].manual_overrides.len();
+                    if manual_pixels > 0 && ui.button("Clear Edits").clicked() {
+                        log::info!("Clear {} manually edited pixels", manual_pixels);
+                        let before =
+                            std::mem::take(&mut app.images.images[selected_img].manual_overrides);
+                        app.history.record_mask_edit(
+                            &app.images.images[selected_img],
+                            &before,
+                            "Clear edits",
+                        );
+                        app.reload_image(app.images.selected);
+                    }
+
+                    if ui
+                        .button("Import Mask")
+                        .on_hover_text("Turn a pore mask or label map into manual edits, a material mask with the same name is used as well")
+                        .clicked()
+                    {
+                        let path = FileDialog::new()
+                            .add_filter("Mask", &["png", "tif", "tiff"])
+                            .pick_file();
+                        if let Some(path) = path {
+                            match import_masks(app, selected_img, &path) {
+                                Ok((_, 0)) => {}
+                                Ok((_, unreproduced)) => {
+                                    rfd::MessageDialog::new()
+                                        .set_level(rfd::MessageLevel::Warning)
+                                        .set_title("Import Mask")
+                                        .set_description(format!(
+                                            "{} pixels are neither pore nor material in the mask, they keep the result of the analysis.",
+                                            unreproduced
+                                        ))
+                                        .show();
+                                }
+                                Err(err) => show_project_error(&err),
+                            }
+                        }
+                    }
+                }
+            });
+
             if let Some(selected_img) = app.images.selected {
-                if let Some(density) = app.images.images[selected_img].density {
+                let current_image = &app.images.images[selected_img];
+                if let Some(preview) = &current_image.preview {
+                    ui.horizontal(|ui| {
+                        ui.heading(format!("Density: {:.5}%", preview.density));
+                        ui.label("(preview)").on_hover_text(
+                            "Estimated on a downsampled image while a slider is dragged",
+                        );
+                    });
+                } else if let Some(density) = current_image.density {
                     ui.heading(format!("Density: {:.5}%", density));
                 } else {
                     ui.horizontal(|ui| {
                         ui.heading("Density: -".to_string());
 
-                        if app.join_handle.is_some() {
+                        if app.is_analyzing(app.images.images[selected_img].id) {
                             ui.add(Spinner::new());
                         }
                     });
@@ -262,9 +358,18 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
 
             ui.separator();
 
-            ui.heading("Image List");
+            ui.horizontal(|ui| {
+                ui.heading("Image List");
+
+                if !app.images.images.is_empty() && ui.button("Add Images").clicked() {
+                    let images = pick_images(ctx);
+                    add_images(app, images);
+                }
+            });
 
             if !app.images.images.is_empty() {
+                let mut remove = None;
+
                 TableBuilder::new(ui)
                     .id_salt("image_list")
                     .column(Column::auto().at_least(200.0))
@@ -284,8 +389,14 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                             body.row(150.0, |mut row| {
                                 row.set_selected(Some(i) == app.images.selected);
 
-                                row.col(|ui| {
-                                    ui.image(format!("file://{}", path.to_str().unwrap()));
+                                // the texture of large images is downsampled, loading them from the file would decode them as a whole
+                                row.col(|ui| match app.images.images[i].image_handle.clone() {
+                                    Some(handle) => {
+                                        ui.image(&handle);
+                                    }
+                                    None => {
+                                        ui.image(format!("file://{}", path.to_str().unwrap()));
+                                    }
                                 });
                                 row.col(|ui| {
                                     ui.style_mut().interaction.selectable_labels = false;
@@ -296,6 +407,10 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                                         "Density: {:.5}%",
                                         app.images.images[i].density.unwrap_or(0.0),
                                     ));
+
+                                    if ui.button("Remove").clicked() {
+                                        remove = Some(i);
+                                    }
                                 });
 
                                 if row.response().clicked() {
@@ -305,31 +420,39 @@ pub fn display_sidepanel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                             });
                         }
                     });
+
+                if let Some(i) = remove {
+                    remove_image(app, i);
+                }
             } else {
-                // [TODO] make async so the ui is not blocked
                 ui.vertical_centered(|ui| {
                     if ui.button("Open Files").clicked() {
-                        let path = FileDialog::new().pick_files();
-                        if let Some(paths) = path {
-                            for path in &paths {
-                                app.images.images.push(ImageData {
-                                    path: Some(path.to_path_buf()),
-                                    image: Some(image::open(path).unwrap()),
-                                    ..Default::default()
-                                });
-                            }
-
-                            // load texture handles for every image
-                            for image in &mut app.images.images {
-                                image.image_handle =
-                                    Some(load_texture_into_ctx(ctx, &image.image.clone().unwrap()));
-                            }
-
-                            app.images.selected = Some(0);
-                            app.image_to_display = app.images.images[0].image_handle.clone();
-                        }
+                        let images = pick_images(ctx);
+                        add_images(app, images);
                     }
                 });
             }
         });
 }
+
+// [TODO] make async so the ui is not blocked
+fn pick_images(ctx: &egui::Context) -> Vec<ImageData> {
+    let paths = FileDialog::new().pick_files().unwrap_or_default();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            ImageData::open(ctx, path)
+                .inspect_err(|err| log::warn!("{}", err))
+                .ok()
+        })
+        .collect()
+}
+
+// while a slider is dragged only a downsampled preview is analyzed, the full analysis starts once it is released
+fn reload_or_preview(app: &mut PoreDetectionApp, response: &egui::Response) {
+    match app.images.selected {
+        Some(selected_img) if response.dragged() => app.preview_image(selected_img),
+        _ => app.reload_image(app.images.selected),
+    }
+}
@@ -0,0 +1,110 @@
+use egui::Grid;
+use egui_plot::{Bar, BarChart, Legend, Line, Plot, PlotPoints};
+
+use crate::PoreDetectionApp;
+
+const HISTOGRAM_BINS: usize = 20;
+
+pub fn display_statistics_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    egui::Window::new("Spatial Statistics")
+        .open(&mut app.statistics_window_open)
+        .show(ctx, |ui| {
+            let Some(selected_img) = app.images.selected else {
+                ui.label("Load an image to see its statistics.");
+                return;
+            };
+
+            let image = &mut app.images.images[selected_img];
+            image.fill_ripley();
+            let Some(statistics) = &image.spatial_statistics else {
+                ui.label("At least two pores are needed for the spatial statistics.");
+                return;
+            };
+
+            Grid::new("spatial_statistics_grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Pore count");
+                    ui.label(format!("{}", statistics.pore_count));
+                    ui.end_row();
+
+                    ui.label("Mean nearest neighbour distance");
+                    ui.label(format!(
+                        "{:.2} px (std {:.2} px)",
+                        statistics.mean_nearest_neighbour_distance,
+                        statistics.std_nearest_neighbour_distance
+                    ));
+                    ui.end_row();
+
+                    ui.label("Mean spacing");
+                    ui.label(format!("{:.2} px", statistics.mean_spacing));
+                    ui.end_row();
+
+                    ui.label("Clark-Evans index");
+                    let interpretation = if statistics.clark_evans_index < 0.9 {
+                        "clustered"
+                    } else if statistics.clark_evans_index > 1.1 {
+                        "regular"
+                    } else {
+                        "random"
+                    };
+                    ui.label(format!(
+                        "{:.3} ({})",
+                        statistics.clark_evans_index, interpretation
+                    ));
+                    ui.end_row();
+                });
+
+            ui.separator();
+
+            ui.label("Nearest neighbour distances");
+            let max_distance = statistics
+                .nearest_neighbour_distances
+                .iter()
+                .copied()
+                .fold(0.0, f64::max);
+            let bin_width = (max_distance / HISTOGRAM_BINS as f64).max(f64::EPSILON);
+            let mut histogram = [0usize; HISTOGRAM_BINS];
+            for distance in &statistics.nearest_neighbour_distances {
+                let bin = ((distance / bin_width) as usize).min(HISTOGRAM_BINS - 1);
+                histogram[bin] += 1;
+            }
+            let bars = histogram
+                .iter()
+                .enumerate()
+                .map(|(i, count)| {
+                    Bar::new((i as f64 + 0.5) * bin_width, *count as f64).width(bin_width)
+                })
+                .collect::<Vec<_>>();
+
+            Plot::new("nearest_neighbour_plot")
+                .height(180.0)
+                .allow_scroll(false)
+                .x_axis_label("Distance [px]")
+                .y_axis_label("Pores")
+                .show(ui, |plot_ui| {
+                    plot_ui.bar_chart(BarChart::new(bars));
+                });
+
+            ui.label("Ripley's L function");
+            let ripley = statistics.ripley.as_deref().unwrap_or_default();
+            let l_points: PlotPoints<'_> =
+                ripley.iter().map(|point| [point.radius, point.l]).collect();
+            let random_points: PlotPoints<'_> = ripley
+                .iter()
+                .map(|point| [point.radius, point.radius])
+                .collect();
+
+            Plot::new("ripley_plot")
+                .height(180.0)
+                .allow_scroll(false)
+                .legend(Legend::default())
+                .x_axis_label("Radius [px]")
+                .y_axis_label("L(r) [px]")
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(l_points).name("L(r)"));
+                    plot_ui.line(Line::new(random_points).name("Random"));
+                });
+        });
+}
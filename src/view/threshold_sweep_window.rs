@@ -0,0 +1,187 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use egui::{DragValue, ProgressBar};
+use egui_plot::{Line, Plot, PlotPoints, VLine};
+
+use crate::{
+    model::{
+        job::JobControl,
+        threshold_sweep::{run_threshold_sweep, ThresholdSweep},
+    },
+    PoreDetectionApp,
+};
+
+pub struct ThresholdSweepState {
+    pub range_start: i16,
+    pub range_end: i16,
+    pub uncertainty_levels: i16,
+    pub progress: Arc<AtomicUsize>,
+    // of the running sweep, a new one is created for every sweep
+    pub control: Arc<JobControl>,
+    // id of the image and the running sweep
+    pub join_handle: Option<(u64, JoinHandle<Option<ThresholdSweep>>)>,
+}
+
+impl Default for ThresholdSweepState {
+    fn default() -> Self {
+        Self {
+            range_start: 0,
+            range_end: 255,
+            uncertainty_levels: 5,
+            progress: Default::default(),
+            control: Default::default(),
+            join_handle: None,
+        }
+    }
+}
+
+pub fn display_threshold_sweep_window(ctx: &egui::Context, app: &mut PoreDetectionApp) {
+    receive_threshold_sweep(app);
+
+    let mut window_open = app.threshold_sweep_window_open;
+
+    egui::Window::new("Threshold Sweep")
+        .open(&mut window_open)
+        .show(ctx, |ui| {
+            let Some(selected_img) = app.images.selected else {
+                ui.label("Load an image to run a threshold sweep.");
+                return;
+            };
+
+            let state = &mut app.threshold_sweep;
+
+            ui.horizontal(|ui| {
+                ui.label("Thresholds:");
+                ui.add(DragValue::new(&mut state.range_start).range(0..=state.range_end));
+                ui.label("to");
+                ui.add(DragValue::new(&mut state.range_end).range(state.range_start..=255));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Uncertainty: ±");
+                ui.add(DragValue::new(&mut state.uncertainty_levels).range(1..=50));
+                ui.label("grey levels");
+            });
+
+            if let Some((_, handle)) = &state.join_handle {
+                if !handle.is_finished() {
+                    ui.horizontal(|ui| {
+                        let total = (state.range_end - state.range_start + 1) as f32;
+                        let done = state.progress.load(Ordering::Relaxed) as f32;
+                        ui.add(
+                            ProgressBar::new(done / total)
+                                .show_percentage()
+                                .desired_width(200.0),
+                        );
+
+                        if ui.button("Cancel").clicked() {
+                            state.control.cancel();
+                        }
+                    });
+                }
+            } else if ui
+                .button("Run Sweep")
+                .on_hover_text(
+                    "Analyzes the selected image or region for every threshold in the range",
+                )
+                .clicked()
+            {
+                let current_image = &app.images.images[selected_img];
+                state.control = Arc::new(JobControl::default());
+                state.join_handle = Some((
+                    current_image.id,
+                    run_threshold_sweep(
+                        current_image,
+                        state.range_start..=state.range_end,
+                        state.progress.clone(),
+                        state.control.clone(),
+                    ),
+                ));
+            }
+
+            let current_image = &app.images.images[selected_img];
+            let parameters = current_image.analysis_parameters();
+            let Some(sweep) = &current_image.threshold_sweep else {
+                return;
+            };
+
+            ui.separator();
+
+            if !sweep.matches(&parameters) {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    "The parameters have changed since this sweep, run it again.",
+                );
+            }
+
+            // the levels can be changed without running the sweep again
+            let uncertainty_levels = app.threshold_sweep.uncertainty_levels;
+            if let Some(uncertainty) = sweep.uncertainty(parameters.threshold, uncertainty_levels) {
+                ui.heading(format!(
+                    "Density change for ±{} grey levels: {:.5}%",
+                    uncertainty_levels, uncertainty
+                ));
+            } else {
+                ui.label("The current threshold is outside of the sweep range.");
+            }
+
+            let density_points: PlotPoints<'_> = sweep
+                .points
+                .iter()
+                .map(|point| [point.threshold as f64, point.density])
+                .collect();
+            let pore_count_points: PlotPoints<'_> = sweep
+                .points
+                .iter()
+                .map(|point| [point.threshold as f64, point.pore_count as f64])
+                .collect();
+
+            Plot::new("threshold_sweep_density_plot")
+                .height(180.0)
+                .allow_scroll(false)
+                .x_axis_label("Threshold")
+                .y_axis_label("Density [%]")
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(density_points).name("Density"));
+                    plot_ui.vline(VLine::new(parameters.threshold as f64).name("Threshold"));
+                });
+
+            Plot::new("threshold_sweep_pore_count_plot")
+                .height(180.0)
+                .allow_scroll(false)
+                .x_axis_label("Threshold")
+                .y_axis_label("Pores")
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(pore_count_points).name("Pore count"));
+                    plot_ui.vline(VLine::new(parameters.threshold as f64).name("Threshold"));
+                });
+        });
+
+    app.threshold_sweep_window_open = window_open;
+}
+
+fn receive_threshold_sweep(app: &mut PoreDetectionApp) {
+    if let Some((_, handle)) = &app.threshold_sweep.join_handle {
+        if handle.is_finished() {
+            let (image_id, handle) = app.threshold_sweep.join_handle.take().unwrap();
+            // a cancelled sweep keeps the previous one
+            let Some(sweep) = handle.join().unwrap() else {
+                return;
+            };
+
+            if let Some(image) = app
+                .images
+                .images
+                .iter_mut()
+                .find(|image| image.id == image_id)
+            {
+                image.threshold_sweep = Some(sweep);
+            }
+        }
+    }
+}
@@ -1,4 +1,10 @@
-use crate::PoreDetectionApp;
+use crate::{
+    model::{
+        history::{redo, undo},
+        project::save_project_dialog,
+    },
+    PoreDetectionApp,
+};
 
 pub fn display_top_panel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
     egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
@@ -11,11 +17,58 @@ pub fn display_top_panel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                         ui.close_menu();
                     }
 
-                    if ui.button("Load from file").clicked() {
+                    if ui.button("Open").clicked() {
                         app.load_project_model_open = true;
                         ui.close_menu();
                     }
 
+                    if ui.button("Save").clicked() {
+                        save_project_dialog(app, false);
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Save As").clicked() {
+                        save_project_dialog(app, true);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Export Images").clicked() {
+                        app.image_export_window_open = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Batch Analysis").clicked() {
+                        app.batch_window_open = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Depth Profile").clicked() {
+                        app.depth_profile_window_open = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Spatial Statistics").clicked() {
+                        app.statistics_window_open = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Threshold Sweep").clicked() {
+                        app.threshold_sweep_window_open = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Ground Truth").clicked() {
+                        app.ground_truth_window_open = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Display Settings").clicked() {
+                        app.display_settings_window_open = true;
+                        ui.close_menu();
+                    }
+
                     if ui.button("Shortcuts").clicked() {
                         app.shortcut_window_open = true;
                         ui.close_menu();
@@ -30,6 +83,29 @@ pub fn display_top_panel(ctx: &egui::Context, app: &mut PoreDetectionApp) {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
+
+                ui.menu_button("Edit", |ui| {
+                    if ui
+                        .add_enabled(!app.history.undo.is_empty(), egui::Button::new("Undo"))
+                        .clicked()
+                    {
+                        undo(app, ctx);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(!app.history.redo.is_empty(), egui::Button::new("Redo"))
+                        .clicked()
+                    {
+                        redo(app, ctx);
+                        ui.close_menu();
+                    }
+
+                    if ui.button("History").clicked() {
+                        app.history_window_open = true;
+                        ui.close_menu();
+                    }
+                });
                 ui.add_space(16.0);
             }
 